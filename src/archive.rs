@@ -0,0 +1,42 @@
+use crate::{cargo::CargoHold, market::Market, mining::Asteroid, production::ProductionFacilities};
+use bevy::utils::HashMap;
+
+/// What the player left behind in each solar system they have visited
+///
+/// Bodies are despawned on leaving a system, so anything about them which
+/// changes during play is archived here and restored when they respawn.
+#[derive(Debug, Default)]
+pub struct SystemArchive {
+    systems: HashMap<String, ArchivedSystem>,
+}
+
+impl SystemArchive {
+    /// Remember the system called `name` as it was left, replacing any
+    /// earlier visit
+    pub fn store(&mut self, name: &str, system: ArchivedSystem) {
+        self.systems.insert(name.to_string(), system);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ArchivedSystem> {
+        self.systems.get(name)
+    }
+}
+
+/// A solar system as the player left it
+#[derive(Debug, Default, Clone)]
+pub struct ArchivedSystem {
+    /// Simulated time the player left the system
+    pub left_at: f64,
+    /// Keyed by station name
+    pub stations: HashMap<String, ArchivedStation>,
+    /// Keyed by asteroid name
+    pub asteroids: HashMap<String, Asteroid>,
+}
+
+/// A station as the player left it
+#[derive(Debug, Clone)]
+pub struct ArchivedStation {
+    pub cargo: CargoHold,
+    pub market: Option<Market>,
+    pub facilities: Option<ProductionFacilities>,
+}
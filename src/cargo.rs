@@ -0,0 +1,320 @@
+use crate::{
+    item::{ItemCatalog, ItemId},
+    production::ItemStack,
+};
+use bevy::{prelude::*, utils::HashMap};
+use bevy_egui::egui;
+use std::{error::Error, fmt};
+
+/// Why items could not be moved into or out of a cargo hold
+#[derive(Debug, Clone, PartialEq)]
+pub enum CargoError {
+    NoCargoHold(Entity),
+    UnknownItem(ItemId),
+    InsufficientQuantity {
+        item: ItemId,
+        held: u64,
+        requested: u64,
+    },
+    OverMass {
+        needed: f32,
+        free: f32,
+    },
+    OverVolume {
+        needed: f32,
+        free: f32,
+    },
+}
+
+impl fmt::Display for CargoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CargoError::NoCargoHold(entity) => write!(f, "{:?} has no cargo hold", entity),
+            CargoError::UnknownItem(item) => write!(f, "item {} is not in the catalog", item),
+            CargoError::InsufficientQuantity {
+                item,
+                held,
+                requested,
+            } => write!(
+                f,
+                "cannot take {} of item {} when only {} are held",
+                requested, item, held
+            ),
+            CargoError::OverMass { needed, free } => {
+                write!(f, "needs {:.1} t but only {:.1} t is free", needed, free)
+            }
+            CargoError::OverVolume { needed, free } => {
+                write!(f, "needs {:.1} m³ but only {:.1} m³ is free", needed, free)
+            }
+        }
+    }
+}
+
+impl Error for CargoError {}
+
+/// Items carried by a ship or stored at a station, limited by the mass and
+/// volume it can hold
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct CargoHold {
+    items: HashMap<ItemId, u64>,
+    /// Tonnes the hold can carry
+    mass_capacity: f32,
+    /// Cubic metres the hold can carry
+    volume_capacity: f32,
+}
+
+impl CargoHold {
+    pub fn new(mass_capacity: f32, volume_capacity: f32) -> Self {
+        CargoHold {
+            items: HashMap::default(),
+            mass_capacity,
+            volume_capacity,
+        }
+    }
+
+    /// Units of `item` held
+    pub fn quantity(&self, item: ItemId) -> u64 {
+        self.items.get(&item).copied().unwrap_or(0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ItemId, u64)> + '_ {
+        self.items.iter().map(|(&item, &quantity)| (item, quantity))
+    }
+
+    /// Tonnes of cargo held, ignoring items missing from the catalog
+    pub fn mass(&self, catalog: &ItemCatalog) -> f32 {
+        self.iter()
+            .filter_map(|(item, quantity)| catalog.get(item).map(|def| def.mass * quantity as f32))
+            .sum()
+    }
+
+    /// Cubic metres of cargo held, ignoring items missing from the catalog
+    pub fn volume(&self, catalog: &ItemCatalog) -> f32 {
+        self.iter()
+            .filter_map(|(item, quantity)| {
+                catalog.get(item).map(|def| def.volume * quantity as f32)
+            })
+            .sum()
+    }
+
+    /// Check `quantity` units of `item` would fit without storing them
+    pub fn check_store(
+        &self,
+        item: ItemId,
+        quantity: u64,
+        catalog: &ItemCatalog,
+    ) -> Result<(), CargoError> {
+        let def = catalog.get(item).ok_or(CargoError::UnknownItem(item))?;
+
+        let free = self.mass_capacity - self.mass(catalog);
+        let needed = def.mass * quantity as f32;
+        if needed > free {
+            return Err(CargoError::OverMass { needed, free });
+        }
+
+        let free = self.volume_capacity - self.volume(catalog);
+        let needed = def.volume * quantity as f32;
+        if needed > free {
+            return Err(CargoError::OverVolume { needed, free });
+        }
+
+        Ok(())
+    }
+
+    /// Check `quantity` units of `item` are held without removing them
+    pub fn check_remove(&self, item: ItemId, quantity: u64) -> Result<(), CargoError> {
+        let held = self.quantity(item);
+        if held < quantity {
+            return Err(CargoError::InsufficientQuantity {
+                item,
+                held,
+                requested: quantity,
+            });
+        }
+        Ok(())
+    }
+
+    /// Add `quantity` units of `item`, leaving the hold untouched if they do
+    /// not fit
+    pub fn store(
+        &mut self,
+        item: ItemId,
+        quantity: u64,
+        catalog: &ItemCatalog,
+    ) -> Result<(), CargoError> {
+        self.check_store(item, quantity, catalog)?;
+        if quantity > 0 {
+            *self.items.entry(item).or_insert(0) += quantity;
+        }
+        Ok(())
+    }
+
+    /// Take out `quantity` units of `item`, leaving the hold untouched if
+    /// there are not enough
+    pub fn remove(&mut self, item: ItemId, quantity: u64) -> Result<(), CargoError> {
+        self.check_remove(item, quantity)?;
+        if let Some(held) = self.items.get_mut(&item) {
+            *held -= quantity;
+            if *held == 0 {
+                self.items.remove(&item);
+            }
+        }
+        Ok(())
+    }
+
+    /// Take out every one of `stacks`, leaving the hold untouched unless they
+    /// are all there
+    pub fn remove_all(&mut self, stacks: &[ItemStack]) -> Result<(), CargoError> {
+        let mut remaining = self.clone();
+        for stack in stacks {
+            remaining.remove(stack.item, stack.quantity)?;
+        }
+        *self = remaining;
+        Ok(())
+    }
+
+    /// Move `quantity` units of `item` into `other`, changing neither hold
+    /// unless the whole transfer succeeds
+    pub fn transfer(
+        &mut self,
+        other: &mut CargoHold,
+        item: ItemId,
+        quantity: u64,
+        catalog: &ItemCatalog,
+    ) -> Result<(), CargoError> {
+        self.check_remove(item, quantity)?;
+        other.check_store(item, quantity, catalog)?;
+        self.remove(item, quantity)?;
+        other.store(item, quantity, catalog)
+    }
+}
+
+/// Move `quantity` units of `item` between the cargo holds of two entities,
+/// such as a docked ship and its station, leaving both untouched on failure
+pub fn transfer_cargo(
+    world: &mut World,
+    from: Entity,
+    to: Entity,
+    item: ItemId,
+    quantity: u64,
+) -> Result<(), CargoError> {
+    if from == to {
+        return Ok(());
+    }
+
+    let mut source = world
+        .get::<CargoHold>(from)
+        .ok_or(CargoError::NoCargoHold(from))?
+        .clone();
+    let mut destination = world
+        .get::<CargoHold>(to)
+        .ok_or(CargoError::NoCargoHold(to))?
+        .clone();
+    source.transfer(
+        &mut destination,
+        item,
+        quantity,
+        world.resource::<ItemCatalog>(),
+    )?;
+
+    *world
+        .get_mut::<CargoHold>(from)
+        .expect("Expected source to still have a cargo hold") = source;
+    *world
+        .get_mut::<CargoHold>(to)
+        .expect("Expected destination to still have a cargo hold") = destination;
+    Ok(())
+}
+
+/// Show how full `hold` is by mass and by volume
+pub fn capacity_ui(ui: &mut egui::Ui, hold: &CargoHold, catalog: &ItemCatalog) {
+    let mass = hold.mass(catalog);
+    let volume = hold.volume(catalog);
+    ui.add(
+        egui::ProgressBar::new(mass / hold.mass_capacity.max(f32::EPSILON))
+            .text(format!("{:.1} / {:.1} t", mass, hold.mass_capacity)),
+    );
+    ui.add(
+        egui::ProgressBar::new(volume / hold.volume_capacity.max(f32::EPSILON))
+            .text(format!("{:.1} / {:.1} m³", volume, hold.volume_capacity)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DENSE: ItemId = ItemId(1);
+    const BULKY: ItemId = ItemId(2);
+
+    fn catalog() -> ItemCatalog {
+        ItemCatalog::from_json(
+            r#"[
+                {"id": 1, "name": "Dense", "mass": 2.0, "volume": 0.5, "base_price": 10},
+                {"id": 2, "name": "Bulky", "mass": 0.5, "volume": 2.0, "base_price": 10}
+            ]"#,
+        )
+        .expect("Expected test catalog to parse")
+    }
+
+    #[test]
+    fn store_over_mass_fails() {
+        let catalog = catalog();
+        let mut hold = CargoHold::new(10., 10.);
+        assert_eq!(
+            hold.store(DENSE, 6, &catalog),
+            Err(CargoError::OverMass {
+                needed: 12.,
+                free: 10.
+            })
+        );
+        assert_eq!(hold, CargoHold::new(10., 10.));
+    }
+
+    #[test]
+    fn store_over_volume_fails() {
+        let catalog = catalog();
+        let mut hold = CargoHold::new(10., 10.);
+        assert_eq!(
+            hold.store(BULKY, 6, &catalog),
+            Err(CargoError::OverVolume {
+                needed: 12.,
+                free: 10.
+            })
+        );
+        assert_eq!(hold, CargoHold::new(10., 10.));
+    }
+
+    #[test]
+    fn remove_more_than_held_fails() {
+        let catalog = catalog();
+        let mut hold = CargoHold::new(10., 10.);
+        hold.store(DENSE, 3, &catalog).unwrap();
+        assert_eq!(
+            hold.remove(DENSE, 4),
+            Err(CargoError::InsufficientQuantity {
+                item: DENSE,
+                held: 3,
+                requested: 4
+            })
+        );
+        assert_eq!(hold.quantity(DENSE), 3);
+    }
+
+    #[test]
+    fn failed_transfer_leaves_both_holds_unchanged() {
+        let catalog = catalog();
+        let mut from = CargoHold::new(100., 100.);
+        from.store(DENSE, 10, &catalog).unwrap();
+        let mut to = CargoHold::new(10., 10.);
+        to.store(BULKY, 2, &catalog).unwrap();
+        let (from_before, to_before) = (from.clone(), to.clone());
+
+        assert!(matches!(
+            from.transfer(&mut to, DENSE, 5, &catalog),
+            Err(CargoError::OverMass { .. })
+        ));
+        assert_eq!(from, from_before);
+        assert_eq!(to, to_before);
+    }
+}
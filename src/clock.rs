@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use heron::PhysicsTime;
+
+/// Multipliers the simulation can run at, the first being real time
+pub const WARP_SPEEDS: [f64; 6] = [1., 2., 5., 10., 50., 100.];
+
+/// In-game hours which pass for every simulated second
+const HOURS_PER_SECOND: f64 = 1.;
+const START_YEAR: u64 = 2350;
+const DAYS_IN_MONTH: [u64; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+/// Simulation time, which orbits, physics and anything else running on game
+/// time should read instead of [`Time`]
+#[derive(Debug, Default)]
+pub struct SimulationClock {
+    elapsed: f64,
+    delta: f64,
+    paused: bool,
+    warp: usize,
+}
+
+impl SimulationClock {
+    /// Simulated seconds since the game started
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// Simulated seconds which passed during the last frame
+    pub fn delta(&self) -> f64 {
+        self.delta
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// How many times faster than real time the simulation runs, or zero
+    /// while paused
+    pub fn speed(&self) -> f64 {
+        match self.paused {
+            true => 0.,
+            false => WARP_SPEEDS[self.warp],
+        }
+    }
+
+    pub fn warp_up(&mut self) {
+        self.warp = (self.warp + 1).min(WARP_SPEEDS.len() - 1);
+    }
+
+    pub fn warp_down(&mut self) {
+        self.warp = self.warp.saturating_sub(1);
+    }
+
+    /// Advance by `real_delta` seconds of wall-clock time
+    pub fn tick(&mut self, real_delta: f64) {
+        self.delta = real_delta * self.speed();
+        self.elapsed += self.delta;
+    }
+
+    /// In-game date and time as `YYYY-MM-DD HH:MM`, ignoring leap years
+    pub fn date(&self) -> String {
+        let minutes = (self.elapsed * HOURS_PER_SECOND * 60.) as u64;
+        let (hour, minute) = ((minutes / 60) % 24, minutes % 60);
+        let days = minutes / (60 * 24);
+        let year = START_YEAR + days / 365;
+
+        let mut day = days % 365;
+        let mut month = 0;
+        while day >= DAYS_IN_MONTH[month] {
+            day -= DAYS_IN_MONTH[month];
+            month += 1;
+        }
+
+        format!(
+            "{}-{:02}-{:02} {:02}:{:02}",
+            year,
+            month + 1,
+            day + 1,
+            hour,
+            minute
+        )
+    }
+}
+
+pub fn advance_clock(time: Res<Time>, mut clock: ResMut<SimulationClock>) {
+    clock.tick(time.delta_seconds_f64());
+}
+
+/// Run physics at the same rate as the rest of the simulation
+pub fn sync_physics_time(clock: Res<SimulationClock>, mut physics_time: ResMut<PhysicsTime>) {
+    if clock.is_changed() {
+        if clock.is_paused() {
+            physics_time.pause();
+        } else {
+            physics_time.resume();
+            physics_time.set_scale(clock.speed() as f32);
+        }
+    }
+}
+
+pub fn clock_ui(mut egui_ctx: ResMut<EguiContext>, mut clock: ResMut<SimulationClock>) {
+    egui::Window::new("Clock")
+        .anchor(egui::Align2::RIGHT_TOP, [-10., 10.])
+        .title_bar(false)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.monospace(clock.date());
+
+                if ui.button("-").clicked() {
+                    clock.warp_down();
+                }
+
+                let label = match clock.is_paused() {
+                    true => "Paused".to_string(),
+                    false => format!("{}x", clock.speed()),
+                };
+                if ui.button(label).clicked() {
+                    clock.toggle_pause();
+                }
+
+                if ui.button("+").clicked() {
+                    clock.warp_up();
+                }
+            });
+        });
+}
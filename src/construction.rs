@@ -0,0 +1,291 @@
+use crate::{
+    cargo::{CargoError, CargoHold},
+    clock::SimulationClock,
+    item::{ItemCatalog, ItemId},
+    orbit::Orbiting,
+    production::{ItemStack, RecipeBook},
+    spawn_station,
+    universe::{self, Galaxy, StationService},
+    BodyAssets, CurrentSystem, Dockable, Name, Planet, SavePath, Ship, SpawnContext, Star, Station,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use std::{error::Error, fmt};
+
+/// Materials used up building a station, by their IDs in world/items.json
+pub const STATION_COST: [ItemStack; 3] = [
+    ItemStack {
+        item: ItemId(8),
+        quantity: 10,
+    },
+    ItemStack {
+        item: ItemId(9),
+        quantity: 6,
+    },
+    ItemStack {
+        item: ItemId(10),
+        quantity: 2,
+    },
+];
+
+/// Services every player-built station offers, which do not yet include
+/// production since built stations have no facilities
+const BUILT_SERVICES: [StationService; 1] = [StationService::Storage];
+/// Furthest a ship can be from a planet or moon to build a station orbiting
+/// it, beyond which stations orbit the star
+const PLANET_BUILD_RANGE: f32 = 60.;
+/// Closest a station can orbit the body it is built around
+const MIN_ORBIT_RADIUS: f32 = 10.;
+
+/// Why a station could not be built
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstructionError {
+    Docked,
+    NoName,
+    NameTaken(String),
+    NoBody,
+    TooClose { radius: f32, min: f32 },
+    Cargo(CargoError),
+    UnknownMaterial(ItemId),
+}
+
+impl fmt::Display for ConstructionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstructionError::Docked => write!(f, "cannot build while docked"),
+            ConstructionError::NoName => write!(f, "the station needs a name"),
+            ConstructionError::NameTaken(name) => {
+                write!(f, "a station called {} already exists", name)
+            }
+            ConstructionError::NoBody => write!(f, "there is nothing to orbit"),
+            ConstructionError::TooClose { radius, min } => write!(
+                f,
+                "an orbit of {:.0} is too close, it must be at least {:.0}",
+                radius, min
+            ),
+            ConstructionError::Cargo(e) => write!(f, "{}", e),
+            ConstructionError::UnknownMaterial(item) => {
+                write!(f, "station material {} is not in the item catalog", item)
+            }
+        }
+    }
+}
+
+impl Error for ConstructionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConstructionError::Cargo(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Check every material in [`STATION_COST`] is in the catalog
+pub fn check_station_cost(catalog: &ItemCatalog) -> Result<(), ConstructionError> {
+    match STATION_COST
+        .iter()
+        .find(|cost| catalog.get(cost.item).is_none())
+    {
+        Some(cost) => Err(ConstructionError::UnknownMaterial(cost.item)),
+        None => Ok(()),
+    }
+}
+
+/// Take the materials for a station out of `hold`, leaving it untouched
+/// unless they are all there
+pub fn pay_for_station(hold: &mut CargoHold) -> Result<(), CargoError> {
+    hold.remove_all(&STATION_COST)
+}
+
+/// A request to build a station called `name` orbiting whatever `ship` is
+/// nearest to
+pub struct BuildStationEvent {
+    pub ship: Entity,
+    pub name: String,
+}
+
+/// State of the station construction window
+#[derive(Debug, Default)]
+pub struct ConstructionScreen {
+    pub open: bool,
+    name: String,
+    message: Option<String>,
+}
+
+/// Build stations where ships ask to, adding them to the galaxy so they are
+/// still there on returning to the system and in saves
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn build_station(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    mut galaxy: ResMut<Galaxy>,
+    current_system: Res<CurrentSystem>,
+    save_path: Res<SavePath>,
+    clock: Res<SimulationClock>,
+    catalog: Res<ItemCatalog>,
+    recipes: Res<RecipeBook>,
+    mut screen: ResMut<ConstructionScreen>,
+    mut ships: Query<(&GlobalTransform, &Dockable, &mut CargoHold), With<Ship>>,
+    bodies: Query<
+        (Entity, &GlobalTransform, &Name, Option<&Planet>),
+        Or<(With<Planet>, With<Star>)>,
+    >,
+    stations: Query<&Name, With<Station>>,
+    mut build_event: EventReader<BuildStationEvent>,
+) {
+    for build in build_event.iter() {
+        let (ship_transform, dockable, mut cargo) = ships
+            .get_mut(build.ship)
+            .expect("Expected building ship to exist");
+        let ship_position = ship_transform.translation().truncate();
+        let name = build.name.trim();
+
+        // Orbit the nearest planet or moon in range, or else the star
+        let nearest = bodies
+            .iter()
+            .map(|(entity, transform, body_name, planet)| {
+                let offset = ship_position - transform.translation().truncate();
+                (entity, offset, body_name, planet.is_some())
+            })
+            .filter(|(_, offset, _, is_planet)| !is_planet || offset.length() < PLANET_BUILD_RANGE)
+            .min_by(|(_, a, _, a_planet), (_, b, _, b_planet)| {
+                b_planet
+                    .cmp(a_planet)
+                    .then_with(|| a.length().total_cmp(&b.length()))
+            });
+
+        let result = if dockable.is_docked {
+            Err(ConstructionError::Docked)
+        } else if name.is_empty() {
+            Err(ConstructionError::NoName)
+        } else if stations.iter().any(|station| station.0 == name) {
+            Err(ConstructionError::NameTaken(name.to_string()))
+        } else {
+            match nearest {
+                None => Err(ConstructionError::NoBody),
+                Some((_, offset, _, _)) if offset.length() < MIN_ORBIT_RADIUS => {
+                    Err(ConstructionError::TooClose {
+                        radius: offset.length(),
+                        min: MIN_ORBIT_RADIUS,
+                    })
+                }
+                Some(body) => pay_for_station(&mut cargo)
+                    .map(|_| body)
+                    .map_err(ConstructionError::Cargo),
+            }
+        };
+
+        let (body, offset, body_name, is_planet) = match result {
+            Ok(body) => body,
+            Err(e) => {
+                debug!("Cannot build station: {}", e);
+                screen.message = Some(format!("Cannot build station: {}", e));
+                continue;
+            }
+        };
+
+        let radius = offset.length();
+        let station = universe::Station::new(name, radius, None, BUILT_SERVICES.to_vec());
+        let orbit = Orbiting::through(offset, station.orbital_period(radius), clock.elapsed());
+        let station = station.with_phase(orbit.phase);
+
+        let mut assets = BodyAssets {
+            meshes: &mut meshes,
+            materials: &mut materials,
+            font: asset_server.load("fonts/FiraCode-Retina.ttf"),
+        };
+        let context = SpawnContext {
+            time: clock.elapsed(),
+            catalog: &catalog,
+            recipes: &recipes,
+            archived: None,
+        };
+        commands.entity(body).with_children(|parent| {
+            spawn_station(parent, &mut assets, &context, &station, radius, 0);
+        });
+
+        let system = galaxy
+            .system_mut(current_system.0)
+            .expect("Expected current system to exist in the galaxy");
+        let planet = is_planet.then_some(body_name.0.as_str());
+        if let Err(e) = system.add_station(planet, station) {
+            warn!("{}, so {} will not be saved", e, name);
+        }
+        if let Some(path) = &save_path.0 {
+            if let Err(e) = galaxy.to_file(path) {
+                warn!("Failed to save galaxy: {}", e);
+            }
+        }
+
+        debug!("Built {} orbiting {}", name, body_name);
+        screen.message = Some(format!("Built {} orbiting {}", name, body_name));
+        screen.name.clear();
+    }
+}
+
+/// Show the materials a station needs and let the player name and build one
+pub fn construction_ui(
+    mut egui_ctx: ResMut<EguiContext>,
+    mut screen: ResMut<ConstructionScreen>,
+    catalog: Res<ItemCatalog>,
+    ships: Query<(Entity, &CargoHold, &Ship)>,
+    mut build_event: EventWriter<BuildStationEvent>,
+) {
+    let (ship, cargo) = match ships.iter().find(|(_, _, ship)| ship.primary) {
+        Some((ship, cargo, _)) => (ship, cargo),
+        None => return,
+    };
+
+    let ConstructionScreen {
+        open,
+        name,
+        message,
+    } = &mut *screen;
+    egui::Window::new("Build Station")
+        .open(open)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.label("Builds a station orbiting the nearest planet or moon, or else the star");
+            ui.horizontal(|ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(name);
+            });
+
+            ui.separator();
+            let mut affordable = true;
+            egui::Grid::new("station_cost")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("Material");
+                    ui.strong("Needed");
+                    ui.strong("Held");
+                    ui.end_row();
+                    for cost in &STATION_COST {
+                        let held = cargo.quantity(cost.item);
+                        ui.label(catalog.name(cost.item));
+                        ui.label(cost.quantity.to_string());
+                        if held >= cost.quantity {
+                            ui.label(held.to_string());
+                        } else {
+                            affordable = false;
+                            ui.colored_label(egui::Color32::LIGHT_RED, held.to_string());
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            ui.separator();
+            let ready = affordable && !name.trim().is_empty();
+            if ui.add_enabled(ready, egui::Button::new("Build")).clicked() {
+                build_event.send(BuildStationEvent {
+                    ship,
+                    name: name.clone(),
+                });
+            }
+            if let Some(message) = message {
+                ui.label(message.as_str());
+            }
+        });
+}
@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use std::{error::Error, fmt};
+
+/// Seconds a failed docking attempt stays on screen
+const FAILURE_DISPLAY_SECONDS: f64 = 3.;
+
+/// Limits a ship must be within to dock with a station
+#[derive(Debug, Clone, Copy)]
+pub struct DockingRules {
+    /// Distance from a station without docking ports to dock from
+    pub range: f32,
+    /// Distance from a docking port to dock from
+    pub port_range: f32,
+    /// Fastest a ship may move relative to the station
+    pub max_speed: f32,
+    /// Largest angle in radians between the ship's heading and the station
+    /// or docking port
+    pub facing_tolerance: f32,
+    /// Speed a ship is pushed away from the station at when undocking, on
+    /// top of the station's own orbital velocity
+    pub undock_impulse: f32,
+}
+
+impl Default for DockingRules {
+    fn default() -> Self {
+        DockingRules {
+            range: 50.,
+            port_range: 15.,
+            max_speed: 5.,
+            facing_tolerance: 30_f32.to_radians(),
+            undock_impulse: 2.,
+        }
+    }
+}
+
+/// Offsets from a station's centre of each docking port, which ships must
+/// approach head on. Stations without them can be docked with from any side.
+#[derive(Component, Debug, Default, Clone)]
+pub struct DockingPorts(pub Vec<Vec2>);
+
+/// A point a ship may dock at, and how it must be approached
+#[derive(Debug, Clone, Copy)]
+pub struct DockingTarget {
+    pub position: Vec2,
+    /// Direction the ship must be facing
+    pub facing: Vec2,
+    pub range: f32,
+}
+
+/// Why a ship could not dock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DockingError {
+    OutOfRange,
+    TooFast { speed: f32, max: f32 },
+    Misaligned { angle: f32, tolerance: f32 },
+}
+
+impl fmt::Display for DockingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DockingError::OutOfRange => write!(f, "no station or docking port in range"),
+            DockingError::TooFast { speed, max } => {
+                write!(f, "approaching too fast ({:.1}, limit {:.1})", speed, max)
+            }
+            DockingError::Misaligned { angle, tolerance } => write!(
+                f,
+                "misaligned by {:.0}° (tolerance {:.0}°)",
+                angle.to_degrees(),
+                tolerance.to_degrees()
+            ),
+        }
+    }
+}
+
+impl Error for DockingError {}
+
+impl DockingRules {
+    /// Points a ship at `ship` could dock with a station at `station`
+    pub fn targets(
+        &self,
+        station: Vec2,
+        ports: Option<&DockingPorts>,
+        ship: Vec2,
+    ) -> Vec<DockingTarget> {
+        match ports {
+            Some(ports) if !ports.0.is_empty() => ports
+                .0
+                .iter()
+                .map(|&offset| DockingTarget {
+                    position: station + offset,
+                    facing: -offset.normalize_or_zero(),
+                    range: self.port_range,
+                })
+                .collect(),
+            _ => vec![DockingTarget {
+                position: station,
+                facing: (station - ship).normalize_or_zero(),
+                range: self.range,
+            }],
+        }
+    }
+
+    /// Check a ship facing `heading` and moving at `relative_velocity` to a
+    /// target it is within range of may dock there
+    pub fn check(
+        &self,
+        target: &DockingTarget,
+        heading: Vec2,
+        relative_velocity: Vec2,
+    ) -> Result<(), DockingError> {
+        let speed = relative_velocity.length();
+        if speed > self.max_speed {
+            return Err(DockingError::TooFast {
+                speed,
+                max: self.max_speed,
+            });
+        }
+
+        let angle = heading.angle_between(target.facing).abs();
+        if angle > self.facing_tolerance {
+            return Err(DockingError::Misaligned {
+                angle,
+                tolerance: self.facing_tolerance,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// The outcome of the last docking attempt, for the UI
+#[derive(Debug, Default)]
+pub struct DockingStatus {
+    failure: Option<(DockingError, f64)>,
+}
+
+impl DockingStatus {
+    /// Record a failure at `now` seconds since startup
+    pub fn fail(&mut self, error: DockingError, now: f64) {
+        self.failure = Some((error, now));
+    }
+
+    pub fn clear(&mut self) {
+        self.failure = None;
+    }
+}
+
+pub fn docking_status_ui(
+    mut egui_ctx: ResMut<EguiContext>,
+    time: Res<Time>,
+    status: Res<DockingStatus>,
+) {
+    let error = match status.failure {
+        Some((error, at)) if time.seconds_since_startup() - at < FAILURE_DISPLAY_SECONDS => error,
+        _ => return,
+    };
+
+    egui::Window::new("Docking")
+        .anchor(egui::Align2::CENTER_TOP, [0., 10.])
+        .title_bar(false)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.colored_label(
+                egui::Color32::LIGHT_RED,
+                format!("Docking failed: {}", error),
+            );
+        });
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use heron::prelude::*;
+
+/// Gravitational mass of a celestial body, pulling on every dynamic body
+/// within range
+#[derive(Inspectable, Component, Default, Debug, Clone, Copy, PartialEq)]
+pub struct Mass(pub f32);
+
+/// Tuning for how strongly celestial bodies pull on ships
+#[derive(Debug, Clone, Copy)]
+pub struct GravitySettings {
+    /// Gravitational constant multiplying every body's mass
+    pub strength: f32,
+    /// Distance beyond which a body stops pulling
+    pub cutoff: f32,
+    /// Distances closer than this are treated as this far, so flying through
+    /// a body does not fling the ship off at near infinite speed
+    pub min_distance: f32,
+}
+
+impl Default for GravitySettings {
+    fn default() -> Self {
+        GravitySettings {
+            strength: 1.,
+            cutoff: 1000.,
+            min_distance: 10.,
+        }
+    }
+}
+
+impl GravitySettings {
+    /// Acceleration a body of `mass` at `offset` from it applies
+    pub fn acceleration(&self, offset: Vec2, mass: f32) -> Vec2 {
+        let distance = offset.length();
+        if distance > self.cutoff || distance == 0. {
+            return Vec2::ZERO;
+        }
+        let distance = distance.max(self.min_distance);
+        offset.normalize() * self.strength * mass / distance.powi(2)
+    }
+
+    /// Speed of a circular orbit at `radius` around a body of `mass`, or zero
+    /// beyond the cutoff where nothing can be orbited
+    pub fn circular_speed(&self, mass: f32, radius: f32) -> f32 {
+        if radius > self.cutoff {
+            return 0.;
+        }
+        (self.strength * mass / radius.max(self.min_distance)).sqrt()
+    }
+
+    /// Total acceleration towards every well at `position`
+    pub fn acceleration_at(
+        &self,
+        position: Vec2,
+        wells: impl IntoIterator<Item = (Vec2, f32)>,
+    ) -> Vec2 {
+        wells
+            .into_iter()
+            .map(|(well, mass)| self.acceleration(well - position, mass))
+            .fold(Vec2::ZERO, |total, pull| total + pull)
+    }
+}
+
+/// Pull every dynamic rigid body towards the celestial bodies around it
+pub fn apply_gravity(
+    settings: Res<GravitySettings>,
+    wells: Query<(&GlobalTransform, &Mass)>,
+    mut bodies: Query<(&GlobalTransform, &RigidBody, &mut Acceleration)>,
+) {
+    for (transform, body, mut acceleration) in bodies.iter_mut() {
+        if !matches!(body, RigidBody::Dynamic) {
+            continue;
+        }
+
+        let position = transform.translation().truncate();
+        let pull = settings.acceleration_at(
+            position,
+            wells
+                .iter()
+                .map(|(well, mass)| (well.translation().truncate(), mass.0)),
+        );
+        acceleration.linear = pull.extend(0.);
+    }
+}
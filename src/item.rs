@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, fs, io};
+
+/// Stable identifier of an item, as used by cargo holds, markets, recipes
+/// and saves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ItemId(pub u32);
+
+impl fmt::Display for ItemId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Broad kind of an item, from raw ore up to finished goods
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ItemCategory {
+    Ore,
+    Refined,
+    Component,
+    #[default]
+    Commodity,
+}
+
+/// Everything about a kind of item which does not change between stacks of it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemDef {
+    pub id: ItemId,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub category: ItemCategory,
+    /// Tonnes per unit
+    pub mass: f32,
+    /// Cubic metres per unit
+    pub volume: f32,
+    /// Credits per unit at a market holding its usual stock
+    pub base_price: f32,
+    /// Image path relative to the assets folder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+/// Every item in the game, as found in world/items.json
+#[derive(Debug, Default)]
+pub struct ItemCatalog {
+    items: Vec<ItemDef>,
+    ids: HashMap<ItemId, usize>,
+}
+
+impl ItemCatalog {
+    /// Load the catalog from a JSON file on disk
+    pub fn from_file(file_path: &str) -> Result<Self, CatalogError> {
+        let contents = fs::read_to_string(file_path).map_err(|e| CatalogError::Io {
+            path: file_path.to_string(),
+            source: e,
+        })?;
+        ItemCatalog::from_json(&contents)
+    }
+
+    /// Parse a catalog from a JSON list of items
+    pub fn from_json(json: &str) -> Result<Self, CatalogError> {
+        let items: Vec<ItemDef> = serde_json::from_str(json).map_err(CatalogError::Parse)?;
+        let mut catalog = ItemCatalog::default();
+        for item in items {
+            catalog.add(item)?;
+        }
+        Ok(catalog)
+    }
+
+    /// Add an item, failing if its ID is already taken
+    pub fn add(&mut self, item: ItemDef) -> Result<ItemId, CatalogError> {
+        let id = item.id;
+        if self.ids.contains_key(&id) {
+            return Err(CatalogError::DuplicateId(id));
+        }
+        self.ids.insert(id, self.items.len());
+        self.items.push(item);
+        Ok(id)
+    }
+
+    pub fn get(&self, id: ItemId) -> Option<&ItemDef> {
+        self.ids.get(&id).map(|&index| &self.items[index])
+    }
+
+    /// Look up an item by its display name
+    pub fn find(&self, name: &str) -> Option<ItemId> {
+        self.items
+            .iter()
+            .find(|item| item.name == name)
+            .map(|item| item.id)
+    }
+
+    /// Display name of `id`, falling back to the ID itself for unknown items
+    pub fn name(&self, id: ItemId) -> String {
+        self.get(id)
+            .map_or_else(|| format!("Unknown item {}", id), |item| item.name.clone())
+    }
+
+    /// Items in the order they were defined
+    pub fn iter(&self) -> impl Iterator<Item = &ItemDef> {
+        self.items.iter()
+    }
+}
+
+/// Errors which can occur while loading an [`ItemCatalog`]
+#[derive(Debug)]
+pub enum CatalogError {
+    Io { path: String, source: io::Error },
+    Parse(serde_json::Error),
+    DuplicateId(ItemId),
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::Io { path, source } => write!(f, "could not access {}: {}", path, source),
+            CatalogError::Parse(e) => write!(f, "malformed item catalog: {}", e),
+            CatalogError::DuplicateId(id) => write!(f, "item ID {} is used more than once", id),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CatalogError::Io { source, .. } => Some(source),
+            CatalogError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use bevy::{
     diagnostic::LogDiagnosticsPlugin, log::LogSettings, prelude::*, sprite::MaterialMesh2dBundle,
-    utils::HashMap, winit::WinitSettings,
+    winit::WinitSettings,
 };
 use bevy_egui::{egui, EguiContext, EguiPlugin};
 use bevy_inspector_egui::{Inspectable, RegisterInspectable, WorldInspectorPlugin};
@@ -9,25 +9,50 @@ use bevy_prototype_lyon::prelude::*;
 use egui_extras::TableBuilder;
 use heron::prelude::*;
 use leafwing_input_manager::prelude::*;
+mod archive;
+mod cargo;
+mod clock;
+mod construction;
+mod docking;
+mod gravity;
+mod item;
+mod map;
+mod market;
+mod mining;
+mod orbit;
+mod production;
+mod station;
+mod storage;
+mod trajectory;
 mod universe;
-use universe::{debug_universe, Galaxy};
+use archive::{ArchivedStation, ArchivedSystem, SystemArchive};
+use cargo::{capacity_ui, CargoHold};
+use clock::{advance_clock, clock_ui, sync_physics_time, SimulationClock};
+use construction::{
+    build_station, check_station_cost, construction_ui, BuildStationEvent, ConstructionScreen,
+};
+use docking::{docking_status_ui, DockingError, DockingPorts, DockingRules, DockingStatus};
+use gravity::{apply_gravity, GravitySettings, Mass};
+use item::ItemCatalog;
+use map::galaxy_map_ui;
+use market::{update_markets, Credits, Market, MarketTab};
+use mining::{asteroid_orbits, mine_asteroids, mining_ui, regrow_asteroids, Asteroid, Miner};
+use orbit::{
+    style_orbital_paths, sync_orbital_paths, update_orbits, NoOrbitPath, OrbitChain,
+    OrbitPathSettings, Orbiting,
+};
+use production::{run_production, Facility, ProductionFacilities, ProductionTab, RecipeBook};
+use station::{station_screen_ui, ServiceTabs, StationInfo, StationScreen};
+use storage::StorageTab;
+use trajectory::{predict_trajectory, spawn_trajectory_line};
+use universe::{debug_universe, Galaxy, GalaxyParams, Route, RouteCost, StationService, SystemId};
 
 #[derive(Default, Debug)]
 struct UiState {
-    space: bool,
     cargo: bool,
     map: bool,
 }
 
-impl UiState {
-    fn new() -> Self {
-        UiState {
-            space: true,
-            ..default()
-        }
-    }
-}
-
 #[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug)]
 enum Action {
     Thrust,
@@ -39,13 +64,23 @@ enum Action {
     Dock,
     Cargo,
     Map,
+    Jump,
+    Pause,
+    WarpUp,
+    WarpDown,
+    OrbitPaths,
+    Station,
+    Mine,
+    Build,
 }
 
 struct DockEvent(Entity);
 
-#[derive(Inspectable, Component, Default)]
-struct Orbiting {
-    speed: f32,
+struct UndockEvent(Entity);
+
+struct JumpEvent {
+    ship: Entity,
+    destination: SystemId,
 }
 
 #[derive(Inspectable, Component, Default)]
@@ -82,114 +117,369 @@ struct Dockable {
 #[derive(Component, Default)]
 struct DisplayCargo;
 
-#[derive(Component, Default)]
-struct CargoHold {
-    items: HashMap<Item, u64>,
-}
+const STATION_SIZE: f32 = 5.;
+const PORT_SIZE: f32 = 1.5;
+const ASTEROID_COLOR: Color = Color::rgb(0.45, 0.4, 0.35);
+const ARRIVAL_MARGIN: f32 = 100.;
+const DOCKED_ORBIT_PERIOD: f32 = 10.;
+const STARTING_CREDITS: u64 = 1000;
+const SHIP_MASS_CAPACITY: f32 = 50.;
+const SHIP_VOLUME_CAPACITY: f32 = 40.;
+const STATION_MASS_CAPACITY: f32 = 2000.;
+const STATION_VOLUME_CAPACITY: f32 = 1500.;
 
-#[allow(dead_code)]
-impl CargoHold {
-    fn new(items: HashMap<Item, u64>) -> Self {
-        CargoHold { items }
-    }
+/// The solar system the player is currently in
+struct CurrentSystem(SystemId);
 
-    fn store(&mut self, item: Item, quantity: u64) {
-        *self.items.entry(item).or_insert(0) += quantity;
-    }
-
-    fn remove(&mut self, item: Item, quantity: u64) {
-        self.items.entry(item).and_modify(|e| *e -= quantity);
-        self.items.retain(|_, v| *v != 0);
-    }
-}
+/// Where the galaxy is written back to whenever the player changes it, if
+/// anywhere
+struct SavePath(Option<String>);
 
-#[derive(Default, Debug, PartialEq, Eq, Hash)]
-struct Item {
-    name: String,
-    description: String,
+/// The system the player has plotted a course to on the galaxy map, and
+/// what the course minimises
+#[derive(Default)]
+struct Navigation {
+    destination: Option<SystemId>,
+    cost: RouteCost,
 }
 
-impl Item {
-    fn new(name: &str, description: &str) -> Self {
-        Item {
-            name: name.to_string(),
-            description: description.to_string(),
-        }
+impl Navigation {
+    /// Cheapest route from `current` to the plotted destination, if any
+    fn route(&self, galaxy: &Galaxy, current: SystemId) -> Option<Route> {
+        galaxy.route(current, self.destination?, self.cost)
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn spawn_solar_system(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     asset_server: Res<AssetServer>,
+    galaxy: Res<Galaxy>,
+    current_system: Res<CurrentSystem>,
+    clock: Res<SimulationClock>,
+    catalog: Res<ItemCatalog>,
+    recipes: Res<RecipeBook>,
+    archive: Res<SystemArchive>,
 ) {
     debug!("spawn_solar_system");
+    let system = galaxy
+        .system(current_system.0)
+        .expect("Expected current system to exist in the galaxy");
+    spawn_system_bodies(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        asset_server.load("fonts/FiraCode-Retina.ttf"),
+        system,
+        &SpawnContext {
+            time: clock.elapsed(),
+            catalog: &catalog,
+            recipes: &recipes,
+            archived: archive.get(system.name()),
+        },
+    );
+    debug!("Solar system {} spawned", system.name());
+}
+
+/// Meshes, materials and font shared by the bodies of a solar system
+struct BodyAssets<'a> {
+    meshes: &'a mut Assets<Mesh>,
+    materials: &'a mut Assets<ColorMaterial>,
+    font: Handle<Font>,
+}
+
+impl BodyAssets<'_> {
+    fn body(
+        &mut self,
+        size: f32,
+        color: Color,
+        translation: Vec3,
+    ) -> MaterialMesh2dBundle<ColorMaterial> {
+        MaterialMesh2dBundle {
+            mesh: self
+                .meshes
+                .add(shape::Quad::new(Vec2::splat(size)).into())
+                .into(),
+            material: self.materials.add(ColorMaterial::from(color)),
+            transform: Transform::from_translation(translation),
+            ..default()
+        }
+    }
+
+    fn label(&self, name: &str, size: f32) -> Text2dBundle {
+        Text2dBundle {
+            text: Text::from_section(
+                name,
+                TextStyle {
+                    font: self.font.clone(),
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_alignment(TextAlignment::CENTER),
+            transform: Transform::from_translation(Vec3::new(size / 2. + 20., 0., 0.)),
+            ..default()
+        }
+    }
+}
+
+/// What spawned bodies need to know besides how to draw themselves
+struct SpawnContext<'a> {
+    /// Simulated time to place orbiting bodies at
+    time: f64,
+    catalog: &'a ItemCatalog,
+    recipes: &'a RecipeBook,
+    /// How the system was left on the player's last visit, if any
+    archived: Option<&'a ArchivedSystem>,
+}
+
+impl SpawnContext<'_> {
+    fn archived_station(&self, name: &str) -> Option<&ArchivedStation> {
+        self.archived?.stations.get(name)
+    }
+
+    fn archived_asteroid(&self, name: &str) -> Option<&Asteroid> {
+        self.archived?.asteroids.get(name)
+    }
+
+    /// Simulated seconds since the player last left the system
+    fn time_away(&self) -> f64 {
+        self.archived
+            .map_or(0., |archived| self.time - archived.left_at)
+    }
+}
+
+/// Spawn the star, planets and stations of `system` around the origin,
+/// returning the star entity which all other bodies are descendants of
+fn spawn_system_bodies(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    font: Handle<Font>,
+    system: &universe::SolarSystem,
+    context: &SpawnContext,
+) -> Entity {
+    let mut assets = BodyAssets {
+        meshes,
+        materials,
+        font,
+    };
+    let star_class = system.star_class();
+
     commands
         .spawn()
         .insert(Star)
-        .insert(Name("Sol".to_string()))
-        .insert_bundle(SpatialBundle { ..default() })
-        .insert_bundle(MaterialMesh2dBundle {
-            mesh: meshes
-                .add(shape::Quad::new(Vec2::new(50., 50.)).into())
-                .into(),
-            material: materials.add(ColorMaterial::from(Color::ORANGE)),
-            transform: Transform::from_translation(Vec3::new(0., 0., 0.)),
-            ..default()
-        })
+        .insert(Name(system.star().to_string()))
+        .insert(Mass(star_class.mass()))
+        .insert_bundle(assets.body(star_class.size(), star_class.color(), Vec3::ZERO))
+        .insert(Label)
         .with_children(|star| {
-            star.spawn()
-                .insert(Planet)
-                .insert(Name("Earth".to_string()))
-                .insert_bundle(SpatialBundle { ..default() })
-                .insert_bundle(MaterialMesh2dBundle {
-                    mesh: meshes
-                        .add(shape::Quad::new(Vec2::new(10., 10.)).into())
-                        .into(),
-                    material: materials.add(ColorMaterial::from(Color::BLUE)),
-                    transform: Transform::from_translation(Vec3::new(100., 0., 0.)),
-                    ..default()
-                })
-                .insert(Orbiting { speed: 0.001 })
-                .with_children(|planet| {
-                    planet
-                        .spawn()
-                        .insert(Station)
-                        .insert(Name("ISS".to_string()))
-                        .insert_bundle(SpatialBundle { ..default() })
-                        .insert_bundle(MaterialMesh2dBundle {
-                            mesh: meshes
-                                .add(shape::Quad::new(Vec2::new(5., 5.)).into())
-                                .into(),
-                            material: materials.add(ColorMaterial::from(Color::GRAY)),
-                            transform: Transform::from_translation(Vec3::new(25., 0., 0.)),
-                            ..default()
-                        })
-                        .insert(Orbiting { speed: 0.01 })
-                        .insert(Label)
-                        .with_children(|station| {
-                            station.spawn_bundle(Text2dBundle {
-                                text: Text::from_section(
-                                    "ISS",
-                                    TextStyle {
-                                        font: asset_server.load("fonts/FiraCode-Retina.ttf"),
-                                        font_size: 16.0,
-                                        color: Color::WHITE,
-                                    },
-                                )
-                                .with_alignment(TextAlignment::CENTER),
-                                transform: Transform::from_translation(Vec3::new(25.0, 0.0, 0.0)),
-                                ..default()
-                            });
-                        });
-                });
+            star.spawn_bundle(assets.label(system.star(), star_class.size()));
+
+            for (i, planet) in system.planets().iter().enumerate() {
+                spawn_planet(star, &mut assets, context, planet, i);
+            }
+
+            for belt in system.belts() {
+                spawn_belt(star, &mut assets, context, belt);
+            }
+
+            for (i, station) in system.stations().iter().enumerate() {
+                let radius = system.station_orbit_radius(i);
+                spawn_station(star, &mut assets, context, station, radius, i);
+            }
+        })
+        .id()
+}
+
+/// Spawn a planet or moon along with its own moons and stations
+fn spawn_planet(
+    parent: &mut ChildBuilder,
+    assets: &mut BodyAssets,
+    context: &SpawnContext,
+    planet: &universe::Planet,
+    index: usize,
+) {
+    let orbit = Orbiting::new(
+        planet.orbit_radius(),
+        planet.eccentricity(),
+        planet.orbital_period(),
+        orbit_phase(index),
+    );
+    parent
+        .spawn()
+        .insert(Planet)
+        .insert(Name(planet.name().to_string()))
+        .insert(Mass(planet.mass()))
+        .insert_bundle(assets.body(
+            planet.size(),
+            planet.color(),
+            orbit.position_at(context.time).extend(0.),
+        ))
+        .insert(orbit)
+        .insert(Label)
+        .with_children(|parent| {
+            parent.spawn_bundle(assets.label(planet.name(), planet.size()));
+
+            for (i, moon) in planet.moons().iter().enumerate() {
+                spawn_planet(parent, assets, context, moon, i);
+            }
+
+            for (i, station) in planet.stations().iter().enumerate() {
+                let radius = planet.station_orbit_radius(i);
+                spawn_station(parent, assets, context, station, radius, i);
+            }
+        });
+}
+
+/// Spawn a station orbiting `radius` away from its parent, as the `index`th
+/// station around it, returning the station entity
+///
+/// A station the player has visited before gets back the state it was left in.
+fn spawn_station(
+    parent: &mut ChildBuilder,
+    assets: &mut BodyAssets,
+    context: &SpawnContext,
+    station: &universe::Station,
+    radius: f32,
+    index: usize,
+) -> Entity {
+    let archived = context.archived_station(station.name());
+    let phase = station.phase().unwrap_or_else(|| orbit_phase(index));
+    let orbit = Orbiting::circular(radius, station.orbital_period(radius), phase);
+    let ports: Vec<_> = station
+        .docking_ports()
+        .map(|direction| direction * STATION_SIZE / 2.)
+        .collect();
+
+    let mut cargo = archived.map_or_else(
+        || CargoHold::new(STATION_MASS_CAPACITY, STATION_VOLUME_CAPACITY),
+        |archived| archived.cargo.clone(),
+    );
+    let mut facilities = archived
+        .and_then(|archived| archived.facilities.clone())
+        .unwrap_or_else(|| {
+            ProductionFacilities(
+                station
+                    .production()
+                    .iter()
+                    .copied()
+                    .map(Facility::new)
+                    .collect(),
+            )
+        });
+    // Facilities carry on working from storage while the player is away
+    for facility in facilities.0.iter_mut() {
+        let recipe = context
+            .recipes
+            .get(facility.recipe)
+            .expect("Expected facility recipes to be checked when loading the galaxy");
+        facility.run(
+            recipe,
+            &mut cargo,
+            context.time_away() as f32,
+            context.catalog,
+        );
+    }
+
+    let mut entity = parent.spawn();
+    entity
+        .insert(Station)
+        .insert(Name(station.name().to_string()))
+        .insert(cargo)
+        .insert(StationInfo {
+            faction: station.faction().map(|faction| faction.to_string()),
+            services: station.services().to_vec(),
+        })
+        .insert_bundle(assets.body(
+            STATION_SIZE,
+            Color::GRAY,
+            orbit.position_at(context.time).extend(0.),
+        ))
+        .insert(orbit)
+        .insert(Label)
+        .with_children(|parent| {
+            parent.spawn_bundle(assets.label(station.name(), STATION_SIZE));
+            for &port in &ports {
+                parent.spawn_bundle(assets.body(PORT_SIZE, Color::GREEN, port.extend(1.)));
+            }
         });
-    debug!("Solar system spawned");
+    if !ports.is_empty() {
+        entity.insert(DockingPorts(ports));
+    }
+    if station.offers(StationService::Market) {
+        // Markets carry on restocking while the player is away
+        let market = match archived.and_then(|archived| archived.market.as_ref()) {
+            Some(market) => {
+                let mut market = market.clone();
+                market.restock(context.time_away() as f32);
+                market
+            }
+            None => Market::generate(station.name(), context.catalog),
+        };
+        entity.insert(market);
+    }
+    if !facilities.0.is_empty() {
+        entity.insert(facilities);
+    }
+    entity.id()
 }
 
-fn spawn_ship(mut commands: Commands) {
+/// Spawn the asteroids of a belt orbiting the star
+fn spawn_belt(
+    parent: &mut ChildBuilder,
+    assets: &mut BodyAssets,
+    context: &SpawnContext,
+    belt: &universe::AsteroidBelt,
+) {
+    for (i, (orbit, size)) in asteroid_orbits(belt).into_iter().enumerate() {
+        let name = format!("{} {}", belt.name(), i + 1);
+        // Mined asteroids carry on regrowing while the player is away
+        let asteroid = match context.archived_asteroid(&name) {
+            Some(asteroid) => {
+                let mut asteroid = asteroid.clone();
+                asteroid.regrow(context.time_away() as f32);
+                asteroid
+            }
+            None => Asteroid::new(belt.composition().to_vec(), belt.reserve()),
+        };
+        parent
+            .spawn()
+            .insert(Name(name))
+            .insert(asteroid)
+            .insert_bundle(assets.body(
+                size,
+                ASTEROID_COLOR,
+                orbit.position_at(context.time).extend(0.),
+            ))
+            .insert(orbit)
+            .insert(NoOrbitPath);
+    }
+}
+
+/// Orbital phase of the `index`th body sharing a parent, spread out so that
+/// they do not start on top of each other
+fn orbit_phase(index: usize) -> f32 {
+    index as f32 * 2.4
+}
+
+fn spawn_ship(
+    mut commands: Commands,
+    galaxy: Res<Galaxy>,
+    current_system: Res<CurrentSystem>,
+    gravity: Res<GravitySettings>,
+) {
     debug!("spawn_ship");
+    let system = galaxy
+        .system(current_system.0)
+        .expect("Expected current system to exist in the galaxy");
+
+    // Start beyond the outermost planet in a circular orbit of the star, so
+    // the ship neither falls in nor crosses any planet's path
+    let radius = system.outer_radius() + ARRIVAL_MARGIN;
+    let speed = gravity.circular_speed(system.star_class().mass(), radius);
     commands
         .spawn_bundle(GeometryBuilder::build_as(
             &shapes::RegularPolygon {
@@ -201,17 +491,20 @@ fn spawn_ship(mut commands: Commands) {
                 fill_mode: FillMode::color(Color::DARK_GRAY),
                 outline_mode: StrokeMode::new(Color::WHITE, 1.0),
             },
-            Transform::from_translation(Vec3::new(50.0, 0., 0.)),
+            // Pointing along the orbit, anticlockwise like the planets
+            Transform::from_translation(Vec3::new(radius, 0., 0.)),
         ))
         .insert(Ship { primary: true })
         .insert(Dockable { is_docked: false })
-        .insert(CargoHold::default())
+        .insert(CargoHold::new(SHIP_MASS_CAPACITY, SHIP_VOLUME_CAPACITY))
+        .insert(Credits(STARTING_CREDITS))
+        .insert(Miner::default())
         .insert(RigidBody::Dynamic)
         .insert(CollisionShape::Cuboid {
             half_extends: Vec3::splat(3.0),
             border_radius: None,
         })
-        .insert(Velocity::default())
+        .insert(Velocity::from_linear(Vec3::new(0., speed, 0.)))
         .insert(Acceleration::default())
         .insert(RotationConstraints::lock())
         .insert_bundle(InputManagerBundle::<Action> {
@@ -225,6 +518,14 @@ fn spawn_ship(mut commands: Commands) {
                 (KeyCode::Period, Action::Right),
                 (KeyCode::C, Action::Cargo),
                 (KeyCode::M, Action::Map),
+                (KeyCode::J, Action::Jump),
+                (KeyCode::Space, Action::Pause),
+                (KeyCode::RBracket, Action::WarpUp),
+                (KeyCode::LBracket, Action::WarpDown),
+                (KeyCode::O, Action::OrbitPaths),
+                (KeyCode::T, Action::Station),
+                (KeyCode::F, Action::Mine),
+                (KeyCode::B, Action::Build),
             ])
             .insert_chord([KeyCode::LShift, KeyCode::D], Action::Dock)
             .build(),
@@ -232,46 +533,252 @@ fn spawn_ship(mut commands: Commands) {
     debug!("Ship spawned");
 }
 
+#[allow(clippy::too_many_arguments)]
 fn dock_to_nearest(
     mut commands: Commands,
-    query: Query<(&GlobalTransform, Entity), With<Station>>,
-    mut ship_query: Query<(&GlobalTransform, &mut Dockable, Entity), With<Ship>>,
+    query: Query<(&GlobalTransform, Entity, Option<&DockingPorts>), With<Station>>,
+    mut ship_query: Query<(&Transform, &Velocity, &mut Dockable, Entity), With<Ship>>,
+    bodies: Query<(Option<&Orbiting>, Option<&Parent>, &Transform)>,
     mut dock_event: EventReader<DockEvent>,
+    clock: Res<SimulationClock>,
+    time: Res<Time>,
+    rules: Res<DockingRules>,
+    mut status: ResMut<DockingStatus>,
 ) {
     for dock in dock_event.iter() {
-        let (ship_transform, mut dockable, ship_entity) = ship_query
+        let (ship_transform, velocity, mut dockable, ship_entity) = ship_query
             .get_mut(dock.0)
             .expect("Expected docking ship to exist");
 
-        let ship_location = ship_transform.translation();
+        let ship_location = ship_transform.translation.truncate();
 
-        let ds = query
+        // Closest station or docking port the ship is within range of
+        let nearest = query
             .iter()
-            .filter(|(station_transform, _)| {
-                station_transform.translation().distance(ship_location) < 50.0
+            .flat_map(|(station_transform, station, ports)| {
+                rules
+                    .targets(
+                        station_transform.translation().truncate(),
+                        ports,
+                        ship_location,
+                    )
+                    .into_iter()
+                    .map(move |target| (station, station_transform, target))
             })
-            .min_by(|(a, _), (b, _)| {
-                a.translation()
+            .filter(|(_, _, target)| target.position.distance(ship_location) < target.range)
+            .min_by(|(_, _, a), (_, _, b)| {
+                a.position
                     .distance(ship_location)
-                    .total_cmp(&b.translation().distance(ship_location))
+                    .total_cmp(&b.position.distance(ship_location))
             });
 
-        if let Some((_, nearest)) = ds {
-            debug!("Found nearest {:?}", nearest);
-            commands
-                .entity(ship_entity)
-                .insert(Orbiting { speed: 0.01 })
-                .remove::<RigidBody>();
-            commands.entity(nearest).add_child(ship_entity);
-            dockable.is_docked = true;
-        } else {
-            debug!("No stations nearby");
+        let result = nearest.ok_or(DockingError::OutOfRange).and_then(
+            |(station, station_transform, target)| {
+                let station_velocity =
+                    OrbitChain::of(station, &bodies).velocity_at(clock.elapsed());
+                rules
+                    .check(
+                        &target,
+                        ship_transform.up().truncate(),
+                        velocity.linear.truncate() - station_velocity,
+                    )
+                    .map(|_| (station, station_transform, station_velocity))
+            },
+        );
+
+        match result {
+            Ok((nearest, station_transform, station_velocity)) => {
+                debug!("Docking with {:?}", nearest);
+                // Switch the ship into the station's frame of reference
+                // straight away, so it does not drift for a frame before its
+                // orbit takes over
+                let offset = ship_location - station_transform.translation().truncate();
+                let orbit = Orbiting::through(offset, DOCKED_ORBIT_PERIOD, clock.elapsed());
+                commands
+                    .entity(ship_entity)
+                    .insert(Transform {
+                        translation: offset.extend(ship_transform.translation.z),
+                        ..*ship_transform
+                    })
+                    .insert(Velocity::from_linear(
+                        (station_velocity + orbit.velocity_at(clock.elapsed())).extend(0.),
+                    ))
+                    .insert(orbit)
+                    .remove::<RigidBody>();
+                commands.entity(nearest).add_child(ship_entity);
+                dockable.is_docked = true;
+                status.clear();
+            }
+            Err(e) => {
+                debug!("Docking failed: {}", e);
+                status.fail(e, time.seconds_since_startup());
+            }
         }
     }
 
     dock_event.clear();
 }
 
+/// Release docked ships into free flight, carrying over the position and
+/// momentum they had while orbiting their station
+fn undock(
+    mut commands: Commands,
+    mut ship_query: Query<(&Transform, &mut Dockable, &Parent), With<Ship>>,
+    bodies: Query<(Option<&Orbiting>, Option<&Parent>, &Transform)>,
+    mut undock_event: EventReader<UndockEvent>,
+    clock: Res<SimulationClock>,
+    rules: Res<DockingRules>,
+) {
+    for undock in undock_event.iter() {
+        let (transform, mut dockable, parent) = ship_query
+            .get_mut(undock.0)
+            .expect("Expected undocking ship to be docked at a station");
+
+        let orbit = OrbitChain::of(undock.0, &bodies);
+        let position = orbit.position_at(clock.elapsed());
+        let impulse = transform.translation.truncate().normalize_or_zero() * rules.undock_impulse;
+        let velocity = orbit.velocity_at(clock.elapsed()) + impulse;
+
+        commands
+            .entity(undock.0)
+            .remove::<Orbiting>()
+            .insert(Transform {
+                translation: position.extend(transform.translation.z),
+                ..*transform
+            })
+            .insert(Velocity::from_linear(velocity.extend(0.)))
+            .insert(RigidBody::Dynamic);
+        commands.entity(parent.get()).remove_children(&[undock.0]);
+        dockable.is_docked = false;
+    }
+
+    undock_event.clear();
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn hyperspace_jump(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    galaxy: Res<Galaxy>,
+    mut current_system: ResMut<CurrentSystem>,
+    mut navigation: ResMut<Navigation>,
+    clock: Res<SimulationClock>,
+    gravity: Res<GravitySettings>,
+    (catalog, recipes): (Res<ItemCatalog>, Res<RecipeBook>),
+    mut archive: ResMut<SystemArchive>,
+    star_query: Query<Entity, With<Star>>,
+    station_query: Query<
+        (
+            &Name,
+            &CargoHold,
+            Option<&Market>,
+            Option<&ProductionFacilities>,
+        ),
+        With<Station>,
+    >,
+    asteroid_query: Query<(&Name, &Asteroid)>,
+    mut ship_query: Query<(&mut Transform, &mut Velocity, &Dockable), With<Ship>>,
+    mut jump_event: EventReader<JumpEvent>,
+) {
+    // Only the last jump requested this frame is made
+    let jump = match jump_event.iter().last() {
+        Some(jump) => jump,
+        None => return,
+    };
+
+    let (mut transform, mut velocity, dockable) = ship_query
+        .get_mut(jump.ship)
+        .expect("Expected jumping ship to exist");
+
+    if dockable.is_docked {
+        debug!("Cannot jump while docked");
+        return;
+    }
+
+    if galaxy
+        .hyperlane(current_system.0, jump.destination)
+        .is_none()
+    {
+        debug!("No hyperlane to {:?}", jump.destination);
+        return;
+    }
+
+    let origin = galaxy
+        .system(current_system.0)
+        .expect("Expected current system to exist in the galaxy");
+    let destination = galaxy
+        .system(jump.destination)
+        .expect("Expected jump destination to exist in the galaxy");
+
+    // Keep what the player leaves behind for when they come back
+    let stations = station_query
+        .iter()
+        .map(|(name, cargo, market, facilities)| {
+            let station = ArchivedStation {
+                cargo: cargo.clone(),
+                market: market.cloned(),
+                facilities: facilities.cloned(),
+            };
+            (name.0.clone(), station)
+        })
+        .collect();
+    let asteroids = asteroid_query
+        .iter()
+        .map(|(name, asteroid)| (name.0.clone(), asteroid.clone()))
+        .collect();
+    archive.store(
+        origin.name(),
+        ArchivedSystem {
+            left_at: clock.elapsed(),
+            stations,
+            asteroids,
+        },
+    );
+
+    for star in star_query.iter() {
+        commands.entity(star).despawn_recursive();
+    }
+
+    spawn_system_bodies(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        asset_server.load("fonts/FiraCode-Retina.ttf"),
+        destination,
+        &SpawnContext {
+            time: clock.elapsed(),
+            catalog: &catalog,
+            recipes: &recipes,
+            archived: archive.get(destination.name()),
+        },
+    );
+
+    // Drop out of hyperspace beyond the outermost planet, on the side facing
+    // the system we came from, pointed towards the star and already in a
+    // circular orbit of it
+    let heading = (origin.position() - destination.position()).normalize_or_zero();
+    let heading = if heading == Vec2::ZERO {
+        Vec2::X
+    } else {
+        heading
+    };
+    let radius = destination.outer_radius() + ARRIVAL_MARGIN;
+    let arrival = heading * radius;
+    transform.translation = arrival.extend(transform.translation.z);
+    transform.rotation = Quat::from_rotation_z(Vec2::Y.angle_between(-heading));
+    let speed = gravity.circular_speed(destination.star_class().mass(), radius);
+    velocity.linear = (heading.perp() * speed).extend(0.);
+
+    debug!("Jumped from {} to {}", origin.name(), destination.name());
+    current_system.0 = jump.destination;
+
+    if navigation.destination == Some(jump.destination) {
+        navigation.destination = None;
+    }
+}
+
 fn spawn_camera(mut commands: Commands) {
     debug!("Spawn camera");
     commands
@@ -390,6 +897,7 @@ fn ship_cargo_ui(
     mut egui_ctx: ResMut<EguiContext>,
     mut ui_state: ResMut<UiState>,
     mut query: Query<(&mut CargoHold, &Ship)>,
+    catalog: Res<ItemCatalog>,
 ) {
     let (mut cargo_hold, _) = query
         .iter_mut()
@@ -403,6 +911,7 @@ fn ship_cargo_ui(
         .show(egui_ctx.ctx_mut(), |ui| {
             ui.vertical(|ui| {
                 ui.heading("Cargo Manifest");
+                capacity_ui(ui, &cargo_hold, &catalog);
                 TableBuilder::new(ui)
                     .resizable(true)
                     .striped(true)
@@ -421,28 +930,35 @@ fn ship_cargo_ui(
                         });
                     })
                     .body(|mut body| {
-                        cargo_hold.items.iter().for_each(|(k, v)| {
+                        cargo_hold.iter().for_each(|(k, v)| {
+                            let item = catalog.get(k);
                             body.row(30.0, |mut row| {
                                 row.col(|ui| {
-                                    ui.label(&k.name);
+                                    ui.label(catalog.name(k));
                                 });
                                 row.col(|ui| {
                                     ui.label(v.to_string());
                                 });
                                 row.col(|ui| {
-                                    ui.label(&k.description);
+                                    ui.label(item.map_or("", |item| item.description.as_str()));
                                 });
                             });
                         });
                     });
-                if ui.button("Add item").clicked() {
-                    cargo_hold.store(Item::new("Iron Ore", "Some iron ore"), 1);
-                    debug!("{:?}", cargo_hold.items);
-                }
+                if let Some(iron_ore) = catalog.find("Iron Ore") {
+                    if ui.button("Add item").clicked() {
+                        if let Err(e) = cargo_hold.store(iron_ore, 1, &catalog) {
+                            debug!("Cannot store item: {}", e);
+                        }
+                        debug!("{:?}", cargo_hold);
+                    }
 
-                if ui.button("Remove item").clicked() {
-                    cargo_hold.remove(Item::new("Iron Ore", "Some iron ore"), 1);
-                    debug!("{:?}", cargo_hold.items);
+                    if ui.button("Remove item").clicked() {
+                        if let Err(e) = cargo_hold.remove(iron_ore, 1) {
+                            debug!("Cannot remove item: {}", e);
+                        }
+                        debug!("{:?}", cargo_hold);
+                    }
                 }
             });
         });
@@ -450,25 +966,26 @@ fn ship_cargo_ui(
     ui_state.set_changed();
 }
 
-#[allow(clippy::type_complexity)]
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 fn handle_actions(
-    mut commands: Commands,
     query: Query<&ActionState<Action>, With<Ship>>,
-    mut ship_query: Query<(
-        Entity,
-        &mut Velocity,
-        &mut Dockable,
-        &mut Transform,
-        Option<&Parent>,
-        &Ship,
-    )>,
+    mut ship_query: Query<(Entity, &mut Velocity, &Dockable, &mut Transform, &Ship)>,
     mut ui_state: ResMut<UiState>,
     mut dock_event: EventWriter<DockEvent>,
+    mut undock_event: EventWriter<UndockEvent>,
+    mut jump_event: EventWriter<JumpEvent>,
+    galaxy: Res<Galaxy>,
+    current_system: Res<CurrentSystem>,
+    navigation: Res<Navigation>,
+    mut clock: ResMut<SimulationClock>,
+    mut orbit_paths: ResMut<OrbitPathSettings>,
+    mut station_screen: ResMut<StationScreen>,
+    mut construction_screen: ResMut<ConstructionScreen>,
 ) {
     let action_state = query.single();
 
-    for (entity, mut velocity, mut dockable, mut transform, parent, _) in
-        ship_query.iter_mut().filter(|(_, _, _, _, _, b)| b.primary)
+    for (entity, mut velocity, dockable, mut transform, _) in
+        ship_query.iter_mut().filter(|(_, _, _, _, b)| b.primary)
     {
         if !dockable.is_docked {
             if action_state.pressed(Action::Left) {
@@ -495,10 +1012,36 @@ fn handle_actions(
                 transform.rotate_local_z(-0.01 * std::f32::consts::PI);
             }
 
+            if action_state.just_pressed(Action::Build) {
+                construction_screen.open = !construction_screen.open;
+            }
+
             if action_state.just_pressed(Action::Cargo) {
                 ui_state.cargo = !ui_state.cargo;
                 ui_state.set_changed();
             }
+
+            if action_state.just_pressed(Action::Jump) {
+                // Follow the plotted course, or failing that jump along the
+                // shortest hyperlane out of the current system
+                let next = navigation
+                    .route(&galaxy, current_system.0)
+                    .and_then(|route| route.next_after(current_system.0))
+                    .or_else(|| {
+                        galaxy
+                            .neighbours(current_system.0)
+                            .min_by(|(_, _, a), (_, _, b)| a.distance.total_cmp(&b.distance))
+                            .map(|(id, _, _)| id)
+                    });
+
+                match next {
+                    Some(destination) => jump_event.send(JumpEvent {
+                        ship: entity,
+                        destination,
+                    }),
+                    None => debug!("No hyperlanes out of the current system"),
+                }
+            }
         }
 
         if action_state.just_pressed(Action::Dock) {
@@ -507,88 +1050,122 @@ fn handle_actions(
                 dock_event.send(DockEvent(entity));
             } else {
                 // If already docked, undock
+                undock_event.send(UndockEvent(entity));
+            }
+        }
 
-                // Ensure parent component exists;
-                let parent_component = parent.expect("Expected docked ship to have parent");
+        if action_state.just_pressed(Action::Map) {
+            ui_state.map = !ui_state.map;
+        }
 
-                // Get parent entity ID
-                let parent_entity = parent_component.get();
+        if action_state.just_pressed(Action::Pause) {
+            clock.toggle_pause();
+        }
 
-                // Remove Orbiting component and add back physics for ship
-                commands
-                    .entity(entity)
-                    .remove::<Orbiting>()
-                    .insert(RigidBody::Dynamic);
+        if action_state.just_pressed(Action::WarpUp) {
+            clock.warp_up();
+        }
 
-                // Remove ship from station children
-                commands.entity(parent_entity).remove_children(&[entity]);
+        if action_state.just_pressed(Action::WarpDown) {
+            clock.warp_down();
+        }
 
-                // Undocking completed
-                dockable.is_docked = false;
-            }
+        if action_state.just_pressed(Action::OrbitPaths) {
+            orbit_paths.visible = !orbit_paths.visible;
         }
 
-        if action_state.just_pressed(Action::Map) {
-            ui_state.map = !ui_state.map;
+        if dockable.is_docked && action_state.just_pressed(Action::Station) {
+            station_screen.open = !station_screen.open;
         }
     }
 }
 
-fn spawn_orbital_paths(
-    mut commands: Commands,
-    query: Query<(Option<&Parent>, &GlobalTransform), With<Orbiting>>,
-    parent_transform_query: Query<&GlobalTransform>,
-) {
-    debug!("spawn_orbital_paths");
-    // Iterate through all GlobalTransforms which Orbit
-    for (parent, transform) in query.iter() {
-        // Get (x, y) global position of parent, or default to (0, 0)
-        let c = if let Some(p) = parent {
-            let parent_transform = parent_transform_query
-                .get(p.get())
-                .expect("Expected parent entity to have a GlobalTransform")
-                .translation();
-            Vec2::new(parent_transform.x, parent_transform.y)
-        } else {
-            Vec2::splat(0.)
-        };
+fn main() {
+    // `--load <path>` starts from a saved galaxy instead of the world file
+    let galaxy_path = std::env::args()
+        .skip_while(|arg| arg != "--load")
+        .nth(1)
+        .unwrap_or_else(|| "world/galaxy.json".to_string());
 
-        // Add orbital path as child to parent of the orbiting body
-        if let Some(p) = parent {
-            commands.entity(p.get()).add_children(|par| {
-                par.spawn_bundle(GeometryBuilder::build_as(
-                    &shapes::Circle {
-                        radius: transform.translation().distance(Vec3::new(c.x, c.y, 0.0)),
-                        center: Vec2::splat(0.),
-                    },
-                    DrawMode::Stroke(StrokeMode::new(Color::WHITE, 2.)),
-                    Transform::default(),
-                ));
-            });
-        } else {
-            commands.spawn_bundle(GeometryBuilder::build_as(
-                &shapes::Circle {
-                    radius: transform.translation().distance(Vec3::new(c.x, c.y, -1.0)),
-                    center: Vec2::splat(0.),
-                },
-                DrawMode::Stroke(StrokeMode::new(Color::WHITE, 2.)),
-                Transform::default(),
-            ));
+    // `--seed <n>` generates a galaxy instead of loading the world file
+    let seed = std::env::args().skip_while(|arg| arg != "--seed").nth(1);
+    let galaxy = match &seed {
+        Some(seed) => match seed.parse() {
+            Ok(seed) => Galaxy::generate(&GalaxyParams::new(seed)),
+            Err(_) => {
+                eprintln!("Invalid galaxy seed \"{}\"", seed);
+                std::process::exit(1);
+            }
+        },
+        None => match Galaxy::from_file(&galaxy_path) {
+            Ok(galaxy) => galaxy,
+            Err(e) => {
+                eprintln!("Failed to load galaxy: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+
+    let catalog = match ItemCatalog::from_file("world/items.json") {
+        Ok(catalog) => catalog,
+        Err(e) => {
+            eprintln!("Failed to load item catalog: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let recipes = match RecipeBook::from_file("world/recipes.json", &catalog) {
+        Ok(recipes) => recipes,
+        Err(e) => {
+            eprintln!("Failed to load recipes: {}", e);
+            std::process::exit(1);
         }
+    };
+
+    if let Err(e) = galaxy.check_references(&catalog, &recipes) {
+        eprintln!("Invalid galaxy: {}", e);
+        std::process::exit(1);
     }
-    debug!("Orbits spawned!");
-}
 
-fn draw_orbiting(mut query: Query<(&mut Transform, &Orbiting)>, ui_state: Res<UiState>) {
-    if ui_state.space {
-        for (mut transform, orbiting) in query.iter_mut() {
-            transform.rotate_around(Vec3::default(), Quat::from_rotation_z(orbiting.speed));
-            transform.rotate_local_z(-orbiting.speed);
+    if let Err(e) = check_station_cost(&catalog) {
+        eprintln!("Invalid station cost: {}", e);
+        std::process::exit(1);
+    }
+
+    let start = match galaxy.systems().next() {
+        Some((id, _)) => CurrentSystem(id),
+        None => {
+            eprintln!("Galaxy has no solar systems to start in");
+            std::process::exit(1);
+        }
+    };
+
+    // `--save <path>` writes the galaxy out straight away, e.g. to check in a
+    // generated world, and again whenever the player builds a station
+    let explicit_save = std::env::args().skip_while(|arg| arg != "--save").nth(1);
+    if let Some(path) = &explicit_save {
+        if let Err(e) = galaxy.to_file(path) {
+            eprintln!("Failed to save galaxy: {}", e);
+            std::process::exit(1);
         }
     }
-}
 
-fn main() {
+    // Otherwise built stations are saved back to the galaxy file loaded, or
+    // next to the game for a generated galaxy, unless `--no-save` is given
+    let save_path = match explicit_save {
+        _ if std::env::args().any(|arg| arg == "--no-save") => SavePath(None),
+        Some(path) => SavePath(Some(path)),
+        None => SavePath(Some(match seed {
+            Some(seed) => format!("galaxy-{}.json", seed),
+            None => galaxy_path,
+        })),
+    };
+
+    let mut service_tabs = ServiceTabs::default();
+    service_tabs.register(MarketTab::default());
+    service_tabs.register(StorageTab::default());
+    service_tabs.register(ProductionTab);
+
     App::new()
         .insert_resource(ClearColor(Color::BLACK))
         .insert_resource(LogSettings {
@@ -596,31 +1173,68 @@ fn main() {
             level: bevy::log::Level::DEBUG,
         })
         .insert_resource(WinitSettings::game())
-        .insert_resource(UiState::new())
-        .insert_resource(Galaxy::from_file("world/galaxy.json"))
+        .insert_resource(UiState::default())
+        .insert_resource(galaxy)
+        .insert_resource(catalog)
+        .insert_resource(recipes)
+        .insert_resource(start)
+        .insert_resource(save_path)
+        .insert_resource(Navigation::default())
+        .insert_resource(SimulationClock::default())
+        .insert_resource(GravitySettings::default())
+        .insert_resource(OrbitPathSettings::default())
+        .insert_resource(DockingRules::default())
+        .insert_resource(DockingStatus::default())
+        .insert_resource(StationScreen::default())
+        .insert_resource(ConstructionScreen::default())
+        .insert_resource(SystemArchive::default())
+        .insert_resource(service_tabs)
         .add_plugins(DefaultPlugins)
         .add_plugin(LogDiagnosticsPlugin::default())
         // .add_plugin(FrameTimeDiagnosticsPlugin::default())
         .add_plugin(WorldInspectorPlugin::new())
-        .add_plugin(PanCamPlugin::default())
+        .add_plugin(PanCamPlugin)
         .add_plugin(InputManagerPlugin::<Action>::default())
         .add_plugin(PhysicsPlugin::default())
         .add_plugin(ShapePlugin)
         .add_plugin(EguiPlugin)
         .register_inspectable::<Orbiting>()
         .register_inspectable::<Name>()
+        .register_inspectable::<Mass>()
         .add_event::<DockEvent>()
+        .add_event::<UndockEvent>()
+        .add_event::<JumpEvent>()
+        .add_event::<BuildStationEvent>()
         .add_startup_system_to_stage(StartupStage::PreStartup, spawn_camera)
         .add_startup_system_to_stage(StartupStage::PreStartup, setup_ui)
         .add_startup_system(spawn_solar_system)
         .add_startup_system(spawn_ship)
-        .add_startup_system_to_stage(StartupStage::PostStartup, spawn_orbital_paths)
+        .add_startup_system(spawn_trajectory_line)
         .add_startup_system_to_stage(StartupStage::PostStartup, debug_universe)
-        .add_system(draw_orbiting)
+        .add_system_to_stage(CoreStage::PreUpdate, advance_clock)
+        .add_system_to_stage(CoreStage::PreUpdate, sync_physics_time)
+        .add_system(update_orbits)
+        .add_system(update_markets)
+        .add_system(mine_asteroids)
+        .add_system(regrow_asteroids)
+        .add_system(run_production)
+        .add_system_to_stage(CoreStage::PostUpdate, sync_orbital_paths)
+        .add_system(style_orbital_paths)
+        .add_system(apply_gravity)
+        .add_system(predict_trajectory)
         .add_system(handle_actions)
         .add_system(handle_ui_click)
         .add_system(ship_cargo_ui)
         .add_system(handle_cargo_button_color)
         .add_system(dock_to_nearest)
+        .add_system(undock)
+        .add_system(hyperspace_jump)
+        .add_system(build_station)
+        .add_system(galaxy_map_ui)
+        .add_system(clock_ui)
+        .add_system(docking_status_ui)
+        .add_system(mining_ui)
+        .add_system(construction_ui)
+        .add_system(station_screen_ui.exclusive_system())
         .run();
 }
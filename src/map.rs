@@ -0,0 +1,139 @@
+use crate::{
+    universe::{Galaxy, RouteCost},
+    CurrentSystem, Navigation, UiState,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+const MAP_PADDING: f32 = 30.;
+const SYSTEM_RADIUS: f32 = 5.;
+const PICK_RADIUS: f32 = 12.;
+
+/// Draw the galaxy map, letting the player pick a destination system
+pub fn galaxy_map_ui(
+    mut egui_ctx: ResMut<EguiContext>,
+    mut ui_state: ResMut<UiState>,
+    galaxy: Res<Galaxy>,
+    current_system: Res<CurrentSystem>,
+    mut navigation: ResMut<Navigation>,
+) {
+    let route = navigation.route(&galaxy, current_system.0);
+
+    egui::Window::new("Galaxy Map")
+        .open(&mut ui_state.map)
+        .default_size([600., 400.])
+        .resizable(true)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                match (
+                    navigation.destination.and_then(|id| galaxy.system(id)),
+                    &route,
+                ) {
+                    (Some(destination), Some(route)) => {
+                        ui.label(format!(
+                            "Destination: {} ({} jumps)",
+                            destination.name(),
+                            route.hops()
+                        ));
+                    }
+                    (Some(destination), None) => {
+                        ui.label(format!("Destination: {} (unreachable)", destination.name()));
+                    }
+                    _ => {
+                        ui.label("Click a system to plot a course");
+                    }
+                }
+
+                if ui.button("Clear").clicked() {
+                    navigation.destination = None;
+                }
+
+                ui.separator();
+                ui.label("Plot by");
+                ui.selectable_value(&mut navigation.cost, RouteCost::Hops, "Jumps");
+                ui.selectable_value(&mut navigation.cost, RouteCost::Distance, "Distance");
+                ui.selectable_value(&mut navigation.cost, RouteCost::TravelCost, "Travel cost");
+            });
+
+            let size = ui.available_size().max(egui::vec2(300., 200.));
+            let (response, painter) = ui.allocate_painter(size, egui::Sense::click());
+            painter.rect_filled(response.rect, 0., egui::Color32::BLACK);
+
+            // Fit the galaxy's bounding box into the canvas, flipping y so
+            // that up on the map matches up in the galaxy
+            let (min, max) = galaxy.systems().fold(
+                (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+                |(min, max), (_, system)| (min.min(system.position()), max.max(system.position())),
+            );
+            let rect = response.rect.shrink(MAP_PADDING);
+            let extent = (max - min).max(Vec2::ONE);
+            let scale = (rect.width() / extent.x).min(rect.height() / extent.y);
+            let centre = (min + max) / 2.;
+            let to_screen = |position: Vec2| {
+                rect.center() + egui::vec2(position.x - centre.x, centre.y - position.y) * scale
+            };
+
+            let on_route = |a, b| {
+                route.as_ref().is_some_and(|route| {
+                    route
+                        .systems
+                        .windows(2)
+                        .any(|w| (w[0] == a && w[1] == b) || (w[0] == b && w[1] == a))
+                })
+            };
+
+            for (a, b, _) in galaxy.hyperlanes() {
+                let stroke = match on_route(a, b) {
+                    true => egui::Stroke::new(3., egui::Color32::GOLD),
+                    false => egui::Stroke::new(1., egui::Color32::DARK_GRAY),
+                };
+                let (a, b) = (&galaxy.graph()[a], &galaxy.graph()[b]);
+                painter.line_segment([to_screen(a.position()), to_screen(b.position())], stroke);
+            }
+
+            let hovered = response.hover_pos().and_then(|pointer| {
+                galaxy
+                    .systems()
+                    .map(|(id, system)| (id, to_screen(system.position()).distance(pointer)))
+                    .filter(|(_, distance)| *distance < PICK_RADIUS)
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(id, _)| id)
+            });
+
+            for (id, system) in galaxy.systems() {
+                let centre = to_screen(system.position());
+                let color = if id == current_system.0 {
+                    egui::Color32::LIGHT_BLUE
+                } else if Some(id) == navigation.destination {
+                    egui::Color32::GOLD
+                } else if route.as_ref().is_some_and(|route| route.contains(id)) {
+                    egui::Color32::LIGHT_YELLOW
+                } else {
+                    egui::Color32::GRAY
+                };
+
+                painter.circle_filled(centre, SYSTEM_RADIUS, color);
+                if Some(id) == hovered {
+                    painter.circle_stroke(
+                        centre,
+                        SYSTEM_RADIUS + 3.,
+                        egui::Stroke::new(1., egui::Color32::WHITE),
+                    );
+                }
+                painter.text(
+                    centre + egui::vec2(0., SYSTEM_RADIUS + 2.),
+                    egui::Align2::CENTER_TOP,
+                    system.name(),
+                    egui::FontId::proportional(12.),
+                    color,
+                );
+            }
+
+            if response.clicked() {
+                if let Some(id) = hovered {
+                    // Picking the current system clears the course
+                    navigation.destination = (id != current_system.0).then_some(id);
+                }
+            }
+        });
+}
@@ -0,0 +1,519 @@
+use crate::{
+    cargo::{capacity_ui, CargoError, CargoHold},
+    clock::SimulationClock,
+    item::{ItemCatalog, ItemId},
+    station::{Docked, ServiceTab},
+    universe::{name_seed, StationService},
+};
+use bevy::{prelude::*, utils::HashMap};
+use bevy_egui::egui;
+use egui_extras::TableBuilder;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::{error::Error, fmt};
+
+/// Chance of a market trading any one item from the catalog
+const TRADE_PROBABILITY: f64 = 0.75;
+
+/// Difference between a market's buy and sell prices, as a fraction of the
+/// going price
+const SPREAD: f32 = 0.1;
+/// How strongly prices react to stock straying from its equilibrium
+const ELASTICITY: f32 = 0.8;
+const MIN_PRICE_FACTOR: f32 = 0.25;
+const MAX_PRICE_FACTOR: f32 = 4.;
+/// Fraction of the gap to equilibrium stock closed per simulated second
+const RESTOCK_RATE: f32 = 0.01;
+
+/// Credits held by a ship's owner
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Credits(pub u64);
+
+/// One item traded at a market
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listing {
+    /// Units the station holds, fractional as it restocks over time
+    pub stock: f32,
+    /// Stock the market drifts back to, and at which it charges the base price
+    pub equilibrium: f32,
+    pub base_price: f32,
+}
+
+impl Listing {
+    /// Units available to buy
+    pub fn available(&self) -> u64 {
+        self.stock.max(0.) as u64
+    }
+
+    /// Going price, rising as stock runs low and falling as it piles up
+    pub fn price(&self) -> f32 {
+        let scarcity = self.equilibrium / self.stock.max(1.);
+        self.base_price
+            * scarcity
+                .powf(ELASTICITY)
+                .clamp(MIN_PRICE_FACTOR, MAX_PRICE_FACTOR)
+    }
+
+    /// Credits a ship pays for one unit
+    pub fn buy_price(&self) -> u64 {
+        (self.price() * (1. + SPREAD)).ceil() as u64
+    }
+
+    /// Credits a ship receives for one unit
+    pub fn sell_price(&self) -> u64 {
+        (self.price() * (1. - SPREAD)).floor() as u64
+    }
+}
+
+/// Why a trade could not go ahead
+#[derive(Debug, Clone, PartialEq)]
+pub enum TradeError {
+    NotTraded(ItemId),
+    OutOfStock { available: u64 },
+    InsufficientCredits { needed: u64, held: u64 },
+    Cargo(CargoError),
+}
+
+impl fmt::Display for TradeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TradeError::NotTraded(id) => write!(f, "item {} is not traded here", id),
+            TradeError::OutOfStock { available } => {
+                write!(f, "only {} in stock", available)
+            }
+            TradeError::InsufficientCredits { needed, held } => {
+                write!(f, "costs {} credits but only {} held", needed, held)
+            }
+            TradeError::Cargo(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for TradeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TradeError::Cargo(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A station's market, where ships buy and sell goods against its stock
+#[derive(Component, Debug, Default, Clone)]
+pub struct Market {
+    listings: HashMap<ItemId, Listing>,
+}
+
+impl Market {
+    /// A market trading most items in the catalog, with the selection and
+    /// stock levels varying by station so prices differ between them
+    pub fn generate(station_name: &str, catalog: &ItemCatalog) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(name_seed(station_name));
+
+        let listings = catalog
+            .iter()
+            .filter_map(|item| {
+                if !rng.gen_bool(TRADE_PROBABILITY) {
+                    return None;
+                }
+                let equilibrium = rng.gen_range(20..200) as f32;
+                let listing = Listing {
+                    stock: equilibrium * rng.gen_range(0.5..1.5),
+                    equilibrium,
+                    base_price: item.base_price,
+                };
+                Some((item.id, listing))
+            })
+            .collect();
+        Market { listings }
+    }
+
+    pub fn listings(&self) -> impl Iterator<Item = (ItemId, &Listing)> {
+        self.listings.iter().map(|(&id, listing)| (id, listing))
+    }
+
+    /// Total cost of buying `quantity` units, with the price rising as each
+    /// unit leaves the station's stock
+    pub fn buy_cost(&self, item: ItemId, quantity: u64) -> Option<u64> {
+        let mut listing = self.listings.get(&item)?.clone();
+        Some(
+            (0..quantity)
+                .map(|_| {
+                    let price = listing.buy_price();
+                    listing.stock -= 1.;
+                    price
+                })
+                .sum(),
+        )
+    }
+
+    /// Total paid for selling `quantity` units, with the price falling as
+    /// each unit joins the station's stock
+    pub fn sell_value(&self, item: ItemId, quantity: u64) -> Option<u64> {
+        let mut listing = self.listings.get(&item)?.clone();
+        Some(
+            (0..quantity)
+                .map(|_| {
+                    let price = listing.sell_price();
+                    listing.stock += 1.;
+                    price
+                })
+                .sum(),
+        )
+    }
+
+    /// Buy `quantity` units into `cargo`, returning the credits spent
+    pub fn buy(
+        &mut self,
+        item: ItemId,
+        quantity: u64,
+        cargo: &mut CargoHold,
+        credits: &mut Credits,
+        catalog: &ItemCatalog,
+    ) -> Result<u64, TradeError> {
+        let cost = self
+            .buy_cost(item, quantity)
+            .ok_or(TradeError::NotTraded(item))?;
+        let listing = self
+            .listings
+            .get_mut(&item)
+            .expect("Expected item with a cost to be listed");
+        if listing.available() < quantity {
+            return Err(TradeError::OutOfStock {
+                available: listing.available(),
+            });
+        }
+        if cost > credits.0 {
+            return Err(TradeError::InsufficientCredits {
+                needed: cost,
+                held: credits.0,
+            });
+        }
+
+        cargo
+            .store(item, quantity, catalog)
+            .map_err(TradeError::Cargo)?;
+        listing.stock -= quantity as f32;
+        credits.0 -= cost;
+        Ok(cost)
+    }
+
+    /// Sell `quantity` units from `cargo`, returning the credits earned
+    pub fn sell(
+        &mut self,
+        item: ItemId,
+        quantity: u64,
+        cargo: &mut CargoHold,
+        credits: &mut Credits,
+    ) -> Result<u64, TradeError> {
+        let value = self
+            .sell_value(item, quantity)
+            .ok_or(TradeError::NotTraded(item))?;
+        cargo.remove(item, quantity).map_err(TradeError::Cargo)?;
+        if let Some(listing) = self.listings.get_mut(&item) {
+            listing.stock += quantity as f32;
+        }
+        credits.0 += value;
+        Ok(value)
+    }
+
+    /// Drift stock back towards equilibrium over `seconds` of simulated
+    /// time, as the station produces and consumes goods
+    pub fn restock(&mut self, seconds: f32) {
+        let fraction = 1. - (-RESTOCK_RATE * seconds).exp();
+        for listing in self.listings.values_mut() {
+            listing.stock += (listing.equilibrium - listing.stock) * fraction;
+        }
+    }
+}
+
+pub fn update_markets(clock: Res<SimulationClock>, mut markets: Query<&mut Market>) {
+    if clock.delta() <= 0. {
+        return;
+    }
+    for mut market in markets.iter_mut() {
+        market.restock(clock.delta() as f32);
+    }
+}
+
+/// The station screen tab for trading with the station's market
+#[derive(Debug)]
+pub struct MarketTab {
+    quantity: u64,
+    message: Option<String>,
+}
+
+impl Default for MarketTab {
+    fn default() -> Self {
+        MarketTab {
+            quantity: 1,
+            message: None,
+        }
+    }
+}
+
+impl ServiceTab for MarketTab {
+    fn service(&self) -> StationService {
+        StationService::Market
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, world: &mut World, docked: Docked) {
+        // Trade on copies and only write them back once a trade succeeds, so
+        // a failed trade leaves everything untouched
+        let (mut market, mut cargo, mut credits) = match (
+            world.get::<Market>(docked.station),
+            world.get::<CargoHold>(docked.ship),
+            world.get::<Credits>(docked.ship),
+        ) {
+            (Some(market), Some(cargo), Some(credits)) => (market.clone(), cargo.clone(), *credits),
+            _ => {
+                ui.label("The market is closed");
+                return;
+            }
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(format!("Credits: {}", credits.0));
+            ui.separator();
+            ui.label("Quantity");
+            ui.add(egui::DragValue::new(&mut self.quantity).clamp_range(1..=1000));
+        });
+
+        let catalog = world.resource::<ItemCatalog>();
+        capacity_ui(ui, &cargo, catalog);
+        let mut items: Vec<_> = market
+            .listings()
+            .map(|(id, listing)| {
+                let description = catalog
+                    .get(id)
+                    .map_or(String::new(), |item| item.description.clone());
+                (id, catalog.name(id), description, listing.clone())
+            })
+            .collect();
+        items.sort_by_key(|(id, ..)| *id);
+
+        let mut trade = None;
+        TableBuilder::new(ui)
+            .striped(true)
+            .column(egui_extras::Size::remainder())
+            .columns(egui_extras::Size::exact(60.), 6)
+            .header(20.0, |mut header| {
+                for title in ["Item", "Stock", "Buy", "Sell", "Held"] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+                header.col(|_| {});
+                header.col(|_| {});
+            })
+            .body(|mut body| {
+                for (id, name, description, listing) in &items {
+                    body.row(24.0, |mut row| {
+                        row.col(|ui| {
+                            ui.label(name).on_hover_text(description);
+                        });
+                        row.col(|ui| {
+                            ui.label(listing.available().to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(listing.buy_price().to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(listing.sell_price().to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(cargo.quantity(*id).to_string());
+                        });
+                        row.col(|ui| {
+                            if ui.button("Buy").clicked() {
+                                trade = Some((*id, name.clone(), true));
+                            }
+                        });
+                        row.col(|ui| {
+                            if ui.button("Sell").clicked() {
+                                trade = Some((*id, name.clone(), false));
+                            }
+                        });
+                    });
+                }
+            });
+
+        if let Some((item, name, buying)) = trade {
+            let catalog = world.resource::<ItemCatalog>();
+            let result = match buying {
+                true => market
+                    .buy(item, self.quantity, &mut cargo, &mut credits, catalog)
+                    .map(|cost| format!("Bought {} {} for {} credits", self.quantity, name, cost)),
+                false => market
+                    .sell(item, self.quantity, &mut cargo, &mut credits)
+                    .map(|value| format!("Sold {} {} for {} credits", self.quantity, name, value)),
+            };
+
+            match result {
+                Ok(message) => {
+                    debug!("{}", message);
+                    self.message = Some(message);
+                    *world
+                        .get_mut::<Market>(docked.station)
+                        .expect("Expected station to have a market") = market;
+                    *world
+                        .get_mut::<CargoHold>(docked.ship)
+                        .expect("Expected ship to have a cargo hold") = cargo;
+                    *world
+                        .get_mut::<Credits>(docked.ship)
+                        .expect("Expected ship to have credits") = credits;
+                }
+                Err(e) => self.message = Some(format!("Cannot trade {}: {}", name, e)),
+            }
+        }
+
+        if let Some(message) = &self.message {
+            ui.separator();
+            ui.label(message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORE: ItemId = ItemId(1);
+
+    fn catalog() -> ItemCatalog {
+        ItemCatalog::from_json(
+            r#"[{"id": 1, "name": "Ore", "mass": 1.0, "volume": 1.0, "base_price": 10}]"#,
+        )
+        .expect("Expected test catalog to parse")
+    }
+
+    fn listing(stock: f32) -> Listing {
+        Listing {
+            stock,
+            equilibrium: 100.,
+            base_price: 10.,
+        }
+    }
+
+    fn market(stock: f32) -> Market {
+        Market {
+            listings: [(ORE, listing(stock))].into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn price_is_base_price_at_equilibrium() {
+        assert_eq!(listing(100.).price(), 10.);
+    }
+
+    #[test]
+    fn price_rises_as_stock_runs_low() {
+        assert!(listing(50.).price() > listing(100.).price());
+        assert_eq!(listing(0.).price(), 10. * MAX_PRICE_FACTOR);
+    }
+
+    #[test]
+    fn price_falls_as_stock_piles_up() {
+        assert!(listing(200.).price() < listing(100.).price());
+        assert_eq!(listing(100_000.).price(), 10. * MIN_PRICE_FACTOR);
+    }
+
+    #[test]
+    fn buy_price_is_above_sell_price() {
+        for stock in [0., 50., 100., 200.] {
+            let listing = listing(stock);
+            assert!(listing.buy_price() as f32 >= listing.price());
+            assert!(listing.sell_price() as f32 <= listing.price());
+        }
+    }
+
+    #[test]
+    fn buy_moves_goods_for_credits() {
+        let catalog = catalog();
+        let mut market = market(100.);
+        let mut cargo = CargoHold::new(10., 10.);
+        let mut credits = Credits(1000);
+
+        let cost = market.buy_cost(ORE, 2).unwrap();
+        assert_eq!(
+            market.buy(ORE, 2, &mut cargo, &mut credits, &catalog),
+            Ok(cost)
+        );
+        assert_eq!(credits, Credits(1000 - cost));
+        assert_eq!(cargo.quantity(ORE), 2);
+        assert_eq!(market.listings[&ORE].stock, 98.);
+    }
+
+    #[test]
+    fn buy_without_credits_fails() {
+        let catalog = catalog();
+        let mut market = market(100.);
+        let mut cargo = CargoHold::new(10., 10.);
+        let mut credits = Credits(5);
+
+        assert_eq!(
+            market.buy(ORE, 1, &mut cargo, &mut credits, &catalog),
+            Err(TradeError::InsufficientCredits {
+                needed: listing(100.).buy_price(),
+                held: 5
+            })
+        );
+        assert_eq!(credits, Credits(5));
+        assert_eq!(cargo, CargoHold::new(10., 10.));
+        assert_eq!(market.listings[&ORE], listing(100.));
+    }
+
+    #[test]
+    fn buy_more_than_stock_fails() {
+        let catalog = catalog();
+        let mut market = market(3.);
+        let mut cargo = CargoHold::new(10., 10.);
+        let mut credits = Credits(1000);
+
+        assert_eq!(
+            market.buy(ORE, 4, &mut cargo, &mut credits, &catalog),
+            Err(TradeError::OutOfStock { available: 3 })
+        );
+        assert_eq!(credits, Credits(1000));
+        assert_eq!(cargo, CargoHold::new(10., 10.));
+        assert_eq!(market.listings[&ORE], listing(3.));
+    }
+
+    #[test]
+    fn buy_without_cargo_space_fails() {
+        let catalog = catalog();
+        let mut market = market(100.);
+        let mut cargo = CargoHold::new(2., 10.);
+        let mut credits = Credits(1000);
+
+        assert!(matches!(
+            market.buy(ORE, 3, &mut cargo, &mut credits, &catalog),
+            Err(TradeError::Cargo(CargoError::OverMass { .. }))
+        ));
+        assert_eq!(credits, Credits(1000));
+        assert_eq!(cargo, CargoHold::new(2., 10.));
+        assert_eq!(market.listings[&ORE], listing(100.));
+    }
+
+    #[test]
+    fn sell_more_than_held_fails() {
+        let catalog = catalog();
+        let mut market = market(100.);
+        let mut cargo = CargoHold::new(10., 10.);
+        cargo.store(ORE, 1, &catalog).unwrap();
+        let before = cargo.clone();
+        let mut credits = Credits(0);
+
+        assert_eq!(
+            market.sell(ORE, 2, &mut cargo, &mut credits),
+            Err(TradeError::Cargo(CargoError::InsufficientQuantity {
+                item: ORE,
+                held: 1,
+                requested: 2
+            }))
+        );
+        assert_eq!(credits, Credits(0));
+        assert_eq!(cargo, before);
+        assert_eq!(market.listings[&ORE], listing(100.));
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{
+    cargo::{CargoError, CargoHold},
+    clock::SimulationClock,
+    item::{ItemCatalog, ItemId},
+    orbit::Orbiting,
+    universe::{name_seed, AsteroidBelt, OreShare},
+    Action, Dockable, Name, Ship,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use leafwing_input_manager::prelude::*;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+/// Fraction of the gap to a full reserve regrown per simulated second
+const REGROWTH_RATE: f32 = 0.002;
+/// Drawn scale of an asteroid mined down to nothing
+const DEPLETED_SCALE: f32 = 0.3;
+const MIN_ASTEROID_SIZE: f32 = 1.5;
+const MAX_ASTEROID_SIZE: f32 = 4.;
+
+/// A minable asteroid, holding a reserve of ore which slowly regrows
+#[derive(Component, Debug, Clone)]
+pub struct Asteroid {
+    composition: Vec<OreShare>,
+    /// Units of ore left, fractional as it regrows over time
+    reserve: f32,
+    capacity: f32,
+}
+
+impl Asteroid {
+    pub fn new(composition: Vec<OreShare>, capacity: f32) -> Self {
+        Asteroid {
+            composition,
+            reserve: capacity,
+            capacity,
+        }
+    }
+
+    pub fn composition(&self) -> &[OreShare] {
+        &self.composition
+    }
+
+    pub fn reserve(&self) -> f32 {
+        self.reserve
+    }
+
+    pub fn capacity(&self) -> f32 {
+        self.capacity
+    }
+
+    /// How much of its ore the asteroid still holds, from 0 to 1
+    pub fn fraction(&self) -> f32 {
+        self.reserve / self.capacity.max(f32::EPSILON)
+    }
+
+    /// Whether there is not a whole unit of ore left to mine
+    pub fn is_depleted(&self) -> bool {
+        self.reserve < 1.
+    }
+
+    /// The ore the next unit mined will be, picked in proportion to the
+    /// asteroid's composition
+    pub fn pick_ore<R: Rng>(&self, rng: &mut R) -> Option<ItemId> {
+        if self.is_depleted() {
+            return None;
+        }
+        self.composition
+            .choose_weighted(rng, |ore| ore.share)
+            .ok()
+            .map(|ore| ore.item)
+    }
+
+    /// Remove `units` of ore from the reserve
+    pub fn take(&mut self, units: f32) {
+        self.reserve = (self.reserve - units).max(0.);
+    }
+
+    /// Regrow the reserve towards capacity over `seconds` of simulated time
+    pub fn regrow(&mut self, seconds: f32) {
+        let fraction = 1. - (-REGROWTH_RATE * seconds).exp();
+        self.reserve += (self.capacity - self.reserve) * fraction;
+    }
+}
+
+/// Orbits and sizes of the asteroids of `belt`, scattered across its width
+/// and around the star the same way every time the system is visited
+pub fn asteroid_orbits(belt: &AsteroidBelt) -> Vec<(Orbiting, f32)> {
+    let mut rng = ChaCha8Rng::seed_from_u64(name_seed(belt.name()));
+
+    let half_width = belt.width() / 2.;
+    (0..belt.asteroids())
+        .map(|_| {
+            let radius = belt.orbit_radius() + rng.gen_range(-half_width..=half_width);
+            let orbit = Orbiting::new(
+                radius,
+                rng.gen_range(0.0..0.05),
+                belt.orbital_period(radius),
+                rng.gen_range(0.0..std::f32::consts::TAU),
+            );
+            (orbit, rng.gen_range(MIN_ASTEROID_SIZE..MAX_ASTEROID_SIZE))
+        })
+        .collect()
+}
+
+/// Mining equipment fitted to a ship
+#[derive(Component, Debug, Clone)]
+pub struct Miner {
+    /// Units of ore extracted per simulated second
+    pub rate: f32,
+    /// Furthest an asteroid can be from the ship to be mined
+    pub range: f32,
+    /// Whether the ship is trying to mine this frame
+    active: bool,
+    /// Asteroid being mined, if one is in range
+    target: Option<Entity>,
+    /// Fraction of the next unit of ore extracted so far
+    progress: f32,
+    /// Why the last unit of ore could not be stored
+    failure: Option<CargoError>,
+}
+
+impl Default for Miner {
+    fn default() -> Self {
+        Miner {
+            rate: 0.5,
+            range: 20.,
+            active: false,
+            target: None,
+            progress: 0.,
+            failure: None,
+        }
+    }
+}
+
+impl Miner {
+    fn stop(&mut self) {
+        self.active = false;
+        self.target = None;
+        self.progress = 0.;
+        self.failure = None;
+    }
+}
+
+/// Extract ore from the nearest asteroid in range into the cargo hold of
+/// ships holding the mine action
+#[allow(clippy::type_complexity)]
+pub fn mine_asteroids(
+    clock: Res<SimulationClock>,
+    catalog: Res<ItemCatalog>,
+    mut ships: Query<(
+        &ActionState<Action>,
+        &GlobalTransform,
+        &Dockable,
+        &mut Miner,
+        &mut CargoHold,
+    )>,
+    mut asteroids: Query<(Entity, &GlobalTransform, &mut Asteroid)>,
+) {
+    let mut rng = thread_rng();
+    for (action_state, transform, dockable, mut miner, mut cargo) in ships.iter_mut() {
+        if dockable.is_docked || !action_state.pressed(Action::Mine) {
+            if miner.active {
+                miner.stop();
+            }
+            continue;
+        }
+        miner.active = true;
+
+        let position = transform.translation().truncate();
+        let target = asteroids
+            .iter()
+            .filter(|(_, _, asteroid)| !asteroid.is_depleted())
+            .map(|(entity, transform, _)| {
+                (
+                    entity,
+                    transform.translation().truncate().distance(position),
+                )
+            })
+            .filter(|(_, distance)| *distance < miner.range)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity);
+
+        if miner.target != target {
+            debug!("Mining target changed to {:?}", target);
+            miner.target = target;
+            miner.progress = 0.;
+        }
+        let target = match target {
+            Some(target) => target,
+            None => continue,
+        };
+
+        let (_, _, mut asteroid) = asteroids
+            .get_mut(target)
+            .expect("Expected asteroid in range to exist");
+        miner.progress += miner.rate * clock.delta() as f32;
+        while miner.progress >= 1. {
+            let ore = match asteroid.pick_ore(&mut rng) {
+                Some(ore) => ore,
+                None => break,
+            };
+            if let Err(e) = cargo.store(ore, 1, &catalog) {
+                miner.progress = 0.;
+                miner.failure = Some(e);
+                break;
+            }
+            asteroid.take(1.);
+            miner.progress -= 1.;
+            miner.failure = None;
+        }
+    }
+}
+
+/// Regrow mined asteroids and shrink them in proportion to the ore they
+/// have left
+pub fn regrow_asteroids(
+    clock: Res<SimulationClock>,
+    mut asteroids: Query<(&mut Asteroid, &mut Transform)>,
+) {
+    for (mut asteroid, mut transform) in asteroids.iter_mut() {
+        if clock.delta() > 0. && asteroid.reserve < asteroid.capacity {
+            asteroid.regrow(clock.delta() as f32);
+        }
+        let scale = DEPLETED_SCALE + (1. - DEPLETED_SCALE) * asteroid.fraction();
+        transform.scale = Vec3::new(scale, scale, 1.);
+    }
+}
+
+/// Show what the primary ship is mining while it mines
+pub fn mining_ui(
+    mut egui_ctx: ResMut<EguiContext>,
+    catalog: Res<ItemCatalog>,
+    ships: Query<(&Miner, &Ship)>,
+    asteroids: Query<(&Asteroid, Option<&Name>)>,
+) {
+    let miner = match ships.iter().find(|(_, ship)| ship.primary) {
+        Some((miner, _)) if miner.active => miner,
+        _ => return,
+    };
+
+    egui::Window::new("Mining")
+        .anchor(egui::Align2::CENTER_BOTTOM, [0., -10.])
+        .title_bar(false)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            let (asteroid, name) = match miner.target.and_then(|target| asteroids.get(target).ok())
+            {
+                Some(found) => found,
+                None => {
+                    ui.label("No asteroid within range");
+                    return;
+                }
+            };
+
+            ui.label(format!(
+                "Mining {}",
+                name.map_or("asteroid".to_string(), |name| name.to_string())
+            ));
+            let ores: Vec<_> = asteroid
+                .composition()
+                .iter()
+                .map(|ore| catalog.name(ore.item))
+                .collect();
+            ui.label(ores.join(", "));
+            ui.add(egui::ProgressBar::new(asteroid.fraction()).text(format!(
+                "{:.0} / {:.0} units left",
+                asteroid.reserve(),
+                asteroid.capacity()
+            )));
+            if let Some(error) = &miner.failure {
+                ui.colored_label(
+                    egui::Color32::LIGHT_RED,
+                    format!("Cannot store ore: {}", error),
+                );
+            }
+        });
+}
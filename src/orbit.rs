@@ -0,0 +1,209 @@
+use crate::clock::SimulationClock;
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use std::f32::consts::{PI, TAU};
+
+mod path;
+pub use path::{style_orbital_paths, sync_orbital_paths, NoOrbitPath, OrbitPathSettings};
+
+const KEPLER_ITERATIONS: usize = 8;
+const MAX_ECCENTRICITY: f32 = 0.99;
+
+/// A Keplerian orbit around the origin of the entity's parent, or the world
+/// origin if it has none
+#[derive(Inspectable, Component, Default, Debug, Clone, Copy, PartialEq)]
+pub struct Orbiting {
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    /// Seconds per orbit
+    pub period: f32,
+    /// Mean anomaly at time zero, in radians
+    pub phase: f32,
+    /// Angle of periapsis from the x axis, in radians
+    pub periapsis: f32,
+}
+
+impl Orbiting {
+    pub fn new(semi_major_axis: f32, eccentricity: f32, period: f32, phase: f32) -> Self {
+        Orbiting {
+            semi_major_axis,
+            eccentricity: eccentricity.clamp(0., MAX_ECCENTRICITY),
+            period,
+            phase,
+            periapsis: 0.,
+        }
+    }
+
+    pub fn circular(radius: f32, period: f32, phase: f32) -> Self {
+        Orbiting::new(radius, 0., period, phase)
+    }
+
+    /// A circular orbit which passes through `offset` from its parent at `time`
+    pub fn through(offset: Vec2, period: f32, time: f64) -> Self {
+        let mut orbit = Orbiting::circular(offset.length(), period, 0.);
+        let angle = offset.y.atan2(offset.x);
+        orbit.phase = (angle - orbit.mean_anomaly(time)).rem_euclid(TAU);
+        orbit
+    }
+
+    pub fn semi_minor_axis(&self) -> f32 {
+        self.semi_major_axis * (1. - self.eccentricity.powi(2)).sqrt()
+    }
+
+    /// Mean anomaly at `time`, in the range `[0, TAU)`
+    pub fn mean_anomaly(&self, time: f64) -> f32 {
+        if self.period <= 0. {
+            return self.phase;
+        }
+        // Wrap in double precision so orbits stay smooth late into a game
+        let orbits = (time / self.period as f64).fract() as f32;
+        (self.phase + orbits * TAU).rem_euclid(TAU)
+    }
+
+    /// Eccentric anomaly at `time`, solving Kepler's equation
+    /// `M = E - e sin E` by Newton's method
+    pub fn eccentric_anomaly(&self, time: f64) -> f32 {
+        let mean = self.mean_anomaly(time);
+        let e = self.eccentricity;
+        let mut anomaly = if e < 0.8 { mean } else { PI };
+        for _ in 0..KEPLER_ITERATIONS {
+            anomaly -= (anomaly - e * anomaly.sin() - mean) / (1. - e * anomaly.cos());
+        }
+        anomaly
+    }
+
+    /// Position relative to the body being orbited at `time`
+    pub fn position_at(&self, time: f64) -> Vec2 {
+        let anomaly = self.eccentric_anomaly(time);
+        let position = Vec2::new(
+            self.semi_major_axis * (anomaly.cos() - self.eccentricity),
+            self.semi_minor_axis() * anomaly.sin(),
+        );
+        Vec2::from_angle(self.periapsis).rotate(position)
+    }
+
+    /// Velocity relative to the body being orbited at `time`
+    pub fn velocity_at(&self, time: f64) -> Vec2 {
+        if self.period <= 0. {
+            return Vec2::ZERO;
+        }
+        let anomaly = self.eccentric_anomaly(time);
+        let rate = TAU / self.period / (1. - self.eccentricity * anomaly.cos());
+        let velocity = Vec2::new(
+            -self.semi_major_axis * anomaly.sin(),
+            self.semi_minor_axis() * anomaly.cos(),
+        ) * rate;
+        Vec2::from_angle(self.periapsis).rotate(velocity)
+    }
+}
+
+/// The orbits a body and each of its ancestors follow, so its motion can be
+/// found at any time without moving the bodies themselves
+pub struct OrbitChain {
+    orbits: Vec<Orbiting>,
+    origin: Vec2,
+}
+
+impl OrbitChain {
+    pub fn of(
+        entity: Entity,
+        bodies: &Query<(Option<&Orbiting>, Option<&Parent>, &Transform)>,
+    ) -> Self {
+        let mut chain = OrbitChain {
+            orbits: Vec::new(),
+            origin: Vec2::ZERO,
+        };
+        let mut current = Some(entity);
+        while let Some(entity) = current {
+            let (orbit, parent, transform) = bodies
+                .get(entity)
+                .expect("Expected body and its ancestors to have a Transform");
+            match orbit {
+                Some(orbit) => chain.orbits.push(*orbit),
+                None => chain.origin += transform.translation.truncate(),
+            }
+            current = parent.map(|parent| parent.get());
+        }
+        chain
+    }
+
+    /// World position at `time`
+    pub fn position_at(&self, time: f64) -> Vec2 {
+        self.origin
+            + self
+                .orbits
+                .iter()
+                .map(|orbit| orbit.position_at(time))
+                .fold(Vec2::ZERO, |total, position| total + position)
+    }
+
+    /// World velocity at `time`
+    pub fn velocity_at(&self, time: f64) -> Vec2 {
+        self.orbits
+            .iter()
+            .map(|orbit| orbit.velocity_at(time))
+            .fold(Vec2::ZERO, |total, velocity| total + velocity)
+    }
+}
+
+/// Move every orbiting body to where its orbit places it at the current
+/// simulation time
+pub fn update_orbits(mut query: Query<(&mut Transform, &Orbiting)>, clock: Res<SimulationClock>) {
+    for (mut transform, orbit) in query.iter_mut() {
+        let position = orbit.position_at(clock.elapsed());
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMES: [f64; 5] = [0., 1.5, 7.25, 40., 1234.5];
+
+    #[test]
+    fn circular_orbit_keeps_its_radius() {
+        let orbit = Orbiting::circular(50., 20., 1.);
+        for time in TIMES {
+            let radius = orbit.position_at(time).length();
+            assert!((radius - 50.).abs() < 1e-3, "radius {} at {}", radius, time);
+        }
+    }
+
+    #[test]
+    fn position_repeats_every_period() {
+        let mut orbit = Orbiting::new(80., 0.6, 20., 0.5);
+        orbit.periapsis = 1.;
+        for time in TIMES {
+            let distance = orbit
+                .position_at(time)
+                .distance(orbit.position_at(time + orbit.period as f64));
+            assert!(
+                distance < 1e-2,
+                "moved {} over a period from {}",
+                distance,
+                time
+            );
+        }
+    }
+
+    #[test]
+    fn velocity_matches_change_in_position() {
+        let mut orbit = Orbiting::new(80., 0.6, 20., 0.5);
+        orbit.periapsis = 1.;
+        let step = 1e-2;
+        for time in TIMES {
+            let estimate = (orbit.position_at(time + step) - orbit.position_at(time - step))
+                / (2. * step) as f32;
+            let velocity = orbit.velocity_at(time);
+            assert!(
+                (estimate - velocity).length() < 0.05 * velocity.length(),
+                "velocity {} but position changing at {} at {}",
+                velocity,
+                estimate,
+                time
+            );
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use super::Orbiting;
+use bevy::{prelude::*, utils::HashMap};
+use bevy_prototype_lyon::prelude::*;
+
+const PATH_COLOR: Color = Color::rgba(1., 1., 1., 0.4);
+
+/// The drawn path of the orbit of `body`, spawned alongside it under the
+/// body it orbits
+#[derive(Component)]
+pub struct OrbitPath {
+    body: Entity,
+}
+
+/// Marks an orbiting body whose path is never drawn, such as one of the many
+/// asteroids of a belt
+#[derive(Component, Default)]
+pub struct NoOrbitPath;
+
+/// How orbital paths are drawn
+#[derive(Debug)]
+pub struct OrbitPathSettings {
+    pub visible: bool,
+    /// Line width in screen pixels, kept the same however far the camera
+    /// zooms out
+    pub width: f32,
+}
+
+impl Default for OrbitPathSettings {
+    fn default() -> Self {
+        OrbitPathSettings {
+            visible: true,
+            width: 1.5,
+        }
+    }
+}
+
+impl OrbitPathSettings {
+    fn draw_mode(&self, scale: f32) -> DrawMode {
+        DrawMode::Stroke(StrokeMode::new(PATH_COLOR, self.width * scale))
+    }
+}
+
+impl Orbiting {
+    /// The ellipse traced by this orbit, and where to place it relative to
+    /// the body being orbited, which sits at one of its foci
+    fn path(&self) -> (shapes::Ellipse, Transform) {
+        let ellipse = shapes::Ellipse {
+            radii: Vec2::new(self.semi_major_axis, self.semi_minor_axis()),
+            center: Vec2::ZERO,
+        };
+        let rotation = Vec2::from_angle(self.periapsis);
+        let centre = rotation.rotate(Vec2::new(-self.semi_major_axis * self.eccentricity, 0.));
+        let transform = Transform::from_translation(centre.extend(-1.))
+            .with_rotation(Quat::from_rotation_z(self.periapsis));
+        (ellipse, transform)
+    }
+}
+
+/// Keep one path per orbiting body, redrawing it when the orbit or the body
+/// it orbits changes and removing it along with the orbit
+///
+/// Runs after [`CoreStage::Update`], since orbits are removed with commands
+/// there and removals are only seen until the end of the frame.
+#[allow(clippy::type_complexity)]
+pub fn sync_orbital_paths(
+    mut commands: Commands,
+    settings: Res<OrbitPathSettings>,
+    changed: Query<
+        (Entity, &Orbiting, Option<&Parent>),
+        (
+            Or<(Changed<Orbiting>, Changed<Parent>)>,
+            Without<NoOrbitPath>,
+        ),
+    >,
+    removed: RemovedComponents<Orbiting>,
+    paths: Query<(Entity, &OrbitPath)>,
+    camera: Query<&OrthographicProjection>,
+) {
+    let mut existing: HashMap<Entity, Entity> = paths
+        .iter()
+        .map(|(path, orbit)| (orbit.body, path))
+        .collect();
+
+    for body in removed.iter() {
+        if let Some(path) = existing.remove(&body) {
+            commands.entity(path).despawn_recursive();
+        }
+    }
+
+    let scale = camera
+        .get_single()
+        .map_or(1., |projection| projection.scale);
+    for (body, orbit, parent) in changed.iter() {
+        if let Some(path) = existing.remove(&body) {
+            commands.entity(path).despawn_recursive();
+        }
+
+        let (ellipse, transform) = orbit.path();
+        let path = commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &ellipse,
+                settings.draw_mode(scale),
+                transform,
+            ))
+            .insert(Visibility {
+                is_visible: settings.visible,
+            })
+            .insert(OrbitPath { body })
+            .id();
+        if let Some(parent) = parent {
+            commands.entity(parent.get()).add_child(path);
+        }
+    }
+}
+
+/// Apply the path settings, scaling line widths with the camera's zoom
+pub fn style_orbital_paths(
+    settings: Res<OrbitPathSettings>,
+    camera: Query<(
+        &OrthographicProjection,
+        ChangeTrackers<OrthographicProjection>,
+    )>,
+    mut paths: Query<(&mut DrawMode, &mut Visibility), With<OrbitPath>>,
+) {
+    let (projection, tracker) = match camera.get_single() {
+        Ok(camera) => camera,
+        _ => return,
+    };
+    if !settings.is_changed() && !tracker.is_changed() {
+        return;
+    }
+
+    for (mut draw_mode, mut visibility) in paths.iter_mut() {
+        *draw_mode = settings.draw_mode(projection.scale);
+        visibility.is_visible = settings.visible;
+    }
+}
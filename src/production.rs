@@ -0,0 +1,454 @@
+use crate::{
+    cargo::{CargoError, CargoHold},
+    clock::SimulationClock,
+    item::{ItemCatalog, ItemId},
+    station::{Docked, ServiceTab},
+    universe::StationService,
+};
+use bevy::prelude::*;
+use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, fs, io};
+
+/// Stable identifier of a recipe, as used by station facilities and saves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RecipeId(pub u32);
+
+impl fmt::Display for RecipeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// A quantity of one item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemStack {
+    pub item: ItemId,
+    pub quantity: u64,
+}
+
+/// Turns a set of input items into output items over a period of time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Recipe {
+    pub id: RecipeId,
+    pub name: String,
+    pub inputs: Vec<ItemStack>,
+    pub outputs: Vec<ItemStack>,
+    /// Simulated seconds to run once
+    pub duration: f32,
+}
+
+impl Recipe {
+    /// Take one run's inputs out of `hold`, leaving it untouched unless
+    /// every input is there
+    pub fn take_inputs(&self, hold: &mut CargoHold) -> Result<(), CargoError> {
+        hold.remove_all(&self.inputs)
+    }
+
+    /// Put one run's outputs into `hold`, leaving it untouched unless they
+    /// all fit
+    pub fn store_outputs(
+        &self,
+        hold: &mut CargoHold,
+        catalog: &ItemCatalog,
+    ) -> Result<(), CargoError> {
+        let mut stored = hold.clone();
+        for output in &self.outputs {
+            stored.store(output.item, output.quantity, catalog)?;
+        }
+        *hold = stored;
+        Ok(())
+    }
+}
+
+/// Every recipe in the game, as found in world/recipes.json
+#[derive(Debug, Default)]
+pub struct RecipeBook {
+    recipes: Vec<Recipe>,
+    ids: HashMap<RecipeId, usize>,
+}
+
+impl RecipeBook {
+    /// Load the recipes from a JSON file on disk, checking them against `catalog`
+    pub fn from_file(file_path: &str, catalog: &ItemCatalog) -> Result<Self, RecipeError> {
+        let contents = fs::read_to_string(file_path).map_err(|e| RecipeError::Io {
+            path: file_path.to_string(),
+            source: e,
+        })?;
+        RecipeBook::from_json(&contents, catalog)
+    }
+
+    /// Parse a JSON list of recipes, checking them against `catalog`
+    pub fn from_json(json: &str, catalog: &ItemCatalog) -> Result<Self, RecipeError> {
+        let recipes: Vec<Recipe> = serde_json::from_str(json).map_err(RecipeError::Parse)?;
+        let mut book = RecipeBook::default();
+        for recipe in recipes {
+            book.add(recipe, catalog)?;
+        }
+        Ok(book)
+    }
+
+    /// Add a recipe, failing if its ID is taken, it uses items missing from
+    /// `catalog` or it takes no time
+    pub fn add(&mut self, recipe: Recipe, catalog: &ItemCatalog) -> Result<RecipeId, RecipeError> {
+        let id = recipe.id;
+        if self.ids.contains_key(&id) {
+            return Err(RecipeError::DuplicateId(id));
+        }
+        if let Some(stack) = recipe
+            .inputs
+            .iter()
+            .chain(&recipe.outputs)
+            .find(|stack| catalog.get(stack.item).is_none())
+        {
+            return Err(RecipeError::UnknownItem {
+                recipe: id,
+                item: stack.item,
+            });
+        }
+        if recipe.duration <= 0. {
+            return Err(RecipeError::InvalidDuration(id));
+        }
+        self.ids.insert(id, self.recipes.len());
+        self.recipes.push(recipe);
+        Ok(id)
+    }
+
+    pub fn get(&self, id: RecipeId) -> Option<&Recipe> {
+        self.ids.get(&id).map(|&index| &self.recipes[index])
+    }
+}
+
+/// Errors which can occur while loading a [`RecipeBook`]
+#[derive(Debug)]
+pub enum RecipeError {
+    Io { path: String, source: io::Error },
+    Parse(serde_json::Error),
+    DuplicateId(RecipeId),
+    UnknownItem { recipe: RecipeId, item: ItemId },
+    InvalidDuration(RecipeId),
+}
+
+impl fmt::Display for RecipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecipeError::Io { path, source } => write!(f, "could not access {}: {}", path, source),
+            RecipeError::Parse(e) => write!(f, "malformed recipes: {}", e),
+            RecipeError::DuplicateId(id) => write!(f, "recipe ID {} is used more than once", id),
+            RecipeError::UnknownItem { recipe, item } => {
+                write!(
+                    f,
+                    "recipe {} uses item {} which is not in the catalog",
+                    recipe, item
+                )
+            }
+            RecipeError::InvalidDuration(id) => {
+                write!(f, "recipe {} must take a positive duration", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecipeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RecipeError::Io { source, .. } => Some(source),
+            RecipeError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A production line at a station, running one recipe over and over
+#[derive(Debug, Clone, PartialEq)]
+pub struct Facility {
+    pub recipe: RecipeId,
+    /// Whether to start new runs once the current one finishes
+    pub enabled: bool,
+    /// Simulated seconds into the current run, whose inputs have already
+    /// been taken
+    elapsed: Option<f32>,
+    /// Why the facility could not start or finish its last run
+    blocked: Option<CargoError>,
+}
+
+impl Facility {
+    pub fn new(recipe: RecipeId) -> Self {
+        Facility {
+            recipe,
+            enabled: true,
+            elapsed: None,
+            blocked: None,
+        }
+    }
+
+    /// How far through its current run the facility is, from 0 to 1
+    pub fn progress(&self, recipe: &Recipe) -> f32 {
+        self.elapsed
+            .map_or(0., |elapsed| (elapsed / recipe.duration).min(1.))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.elapsed.is_some()
+    }
+
+    pub fn blocked(&self) -> Option<&CargoError> {
+        self.blocked.as_ref()
+    }
+
+    /// Run `recipe` for `seconds` of simulated time, taking inputs from and
+    /// putting outputs into `storage`, and return how many runs finished
+    ///
+    /// A run waits for all its inputs before starting, and once finished
+    /// waits for room for all its outputs.
+    pub fn run(
+        &mut self,
+        recipe: &Recipe,
+        storage: &mut CargoHold,
+        seconds: f32,
+        catalog: &ItemCatalog,
+    ) -> u32 {
+        let mut remaining = seconds;
+        let mut finished = 0;
+        loop {
+            match self.elapsed {
+                None => {
+                    if !self.enabled {
+                        self.blocked = None;
+                        break;
+                    }
+                    match recipe.take_inputs(storage) {
+                        Ok(()) => {
+                            self.elapsed = Some(0.);
+                            self.blocked = None;
+                        }
+                        Err(e) => {
+                            self.blocked = Some(e);
+                            break;
+                        }
+                    }
+                }
+                Some(elapsed) => {
+                    let needed = (recipe.duration - elapsed).max(0.);
+                    if remaining < needed {
+                        self.elapsed = Some(elapsed + remaining);
+                        break;
+                    }
+                    remaining -= needed;
+                    match recipe.store_outputs(storage, catalog) {
+                        Ok(()) => {
+                            self.elapsed = None;
+                            self.blocked = None;
+                            finished += 1;
+                        }
+                        Err(e) => {
+                            self.elapsed = Some(recipe.duration);
+                            self.blocked = Some(e);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        finished
+    }
+}
+
+/// The production facilities of a station, which work from its storage
+#[derive(Component, Debug, Default, Clone, PartialEq)]
+pub struct ProductionFacilities(pub Vec<Facility>);
+
+/// Run every station's facilities on the simulation clock
+pub fn run_production(
+    clock: Res<SimulationClock>,
+    catalog: Res<ItemCatalog>,
+    recipes: Res<RecipeBook>,
+    mut stations: Query<(&mut ProductionFacilities, &mut CargoHold)>,
+) {
+    if clock.delta() <= 0. {
+        return;
+    }
+    for (mut facilities, mut storage) in stations.iter_mut() {
+        for facility in facilities.0.iter_mut() {
+            let recipe = recipes
+                .get(facility.recipe)
+                .expect("Expected facility recipes to be checked when loading the galaxy");
+            let finished = facility.run(recipe, &mut storage, clock.delta() as f32, &catalog);
+            if finished > 0 {
+                debug!("{} finished {} runs", recipe.name, finished);
+            }
+        }
+    }
+}
+
+fn stacks_text(stacks: &[ItemStack], catalog: &ItemCatalog) -> String {
+    stacks
+        .iter()
+        .map(|stack| format!("{} {}", stack.quantity, catalog.name(stack.item)))
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// The station screen tab for watching and switching the station's
+/// production facilities
+#[derive(Debug, Default)]
+pub struct ProductionTab;
+
+impl ServiceTab for ProductionTab {
+    fn service(&self) -> StationService {
+        StationService::Production
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, world: &mut World, docked: Docked) {
+        let mut facilities = match world.get::<ProductionFacilities>(docked.station) {
+            Some(facilities) if !facilities.0.is_empty() => facilities.clone(),
+            _ => {
+                ui.label("This station has no production facilities");
+                return;
+            }
+        };
+
+        let catalog = world.resource::<ItemCatalog>();
+        let recipes = world.resource::<RecipeBook>();
+        let mut changed = false;
+        for facility in facilities.0.iter_mut() {
+            let recipe = match recipes.get(facility.recipe) {
+                Some(recipe) => recipe,
+                None => {
+                    ui.label(format!("Unknown recipe {}", facility.recipe));
+                    continue;
+                }
+            };
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.strong(&recipe.name);
+                changed |= ui.checkbox(&mut facility.enabled, "Enabled").changed();
+            });
+            ui.label(format!(
+                "{} → {}",
+                stacks_text(&recipe.inputs, catalog),
+                stacks_text(&recipe.outputs, catalog)
+            ));
+            ui.add(
+                egui::ProgressBar::new(facility.progress(recipe)).text(format!(
+                    "{:.0} / {:.0} s",
+                    facility.progress(recipe) * recipe.duration,
+                    recipe.duration
+                )),
+            );
+            if let Some(error) = facility.blocked() {
+                let reason = match facility.is_running() {
+                    true => "Waiting for room",
+                    false => "Waiting for inputs",
+                };
+                ui.colored_label(egui::Color32::LIGHT_RED, format!("{}: {}", reason, error));
+            }
+        }
+
+        if changed {
+            *world
+                .get_mut::<ProductionFacilities>(docked.station)
+                .expect("Expected station to have production facilities") = facilities;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORE: ItemId = ItemId(1);
+    const INGOT: ItemId = ItemId(2);
+
+    fn catalog() -> ItemCatalog {
+        ItemCatalog::from_json(
+            r#"[
+                {"id": 1, "name": "Ore", "mass": 1.0, "volume": 1.0, "base_price": 10},
+                {"id": 2, "name": "Ingot", "mass": 1.0, "volume": 1.0, "base_price": 30}
+            ]"#,
+        )
+        .expect("Expected test catalog to parse")
+    }
+
+    /// Smelts two ore into `ingots` ingots over ten seconds
+    fn smelting(ingots: u64) -> Recipe {
+        Recipe {
+            id: RecipeId(1),
+            name: "Smelting".to_string(),
+            inputs: vec![ItemStack {
+                item: ORE,
+                quantity: 2,
+            }],
+            outputs: vec![ItemStack {
+                item: INGOT,
+                quantity: ingots,
+            }],
+            duration: 10.,
+        }
+    }
+
+    #[test]
+    fn waits_for_inputs_without_taking_any() {
+        let catalog = catalog();
+        let recipe = smelting(1);
+        let mut storage = CargoHold::new(10., 10.);
+        storage.store(ORE, 1, &catalog).unwrap();
+        let mut facility = Facility::new(recipe.id);
+
+        assert_eq!(facility.run(&recipe, &mut storage, 5., &catalog), 0);
+        assert!(!facility.is_running());
+        assert!(matches!(
+            facility.blocked(),
+            Some(CargoError::InsufficientQuantity { .. })
+        ));
+        assert_eq!(storage.quantity(ORE), 1);
+    }
+
+    #[test]
+    fn waits_for_room_for_outputs() {
+        let catalog = catalog();
+        let recipe = smelting(3);
+        let mut storage = CargoHold::new(2., 10.);
+        storage.store(ORE, 2, &catalog).unwrap();
+        let mut facility = Facility::new(recipe.id);
+
+        assert_eq!(facility.run(&recipe, &mut storage, 15., &catalog), 0);
+        assert!(facility.is_running());
+        assert_eq!(facility.progress(&recipe), 1.);
+        assert!(matches!(
+            facility.blocked(),
+            Some(CargoError::OverMass { .. })
+        ));
+        assert_eq!(storage.quantity(ORE), 0);
+        assert_eq!(storage.quantity(INGOT), 0);
+
+        // Still waiting, without finishing the run or starting another
+        assert_eq!(facility.run(&recipe, &mut storage, 15., &catalog), 0);
+        assert_eq!(facility.progress(&recipe), 1.);
+    }
+
+    #[test]
+    fn completes_a_cycle() {
+        let catalog = catalog();
+        let recipe = smelting(1);
+        let mut storage = CargoHold::new(10., 10.);
+        storage.store(ORE, 3, &catalog).unwrap();
+        let mut facility = Facility::new(recipe.id);
+
+        assert_eq!(facility.run(&recipe, &mut storage, 5., &catalog), 0);
+        assert_eq!(facility.progress(&recipe), 0.5);
+        assert_eq!(storage.quantity(ORE), 1);
+        assert_eq!(storage.quantity(INGOT), 0);
+
+        assert_eq!(facility.run(&recipe, &mut storage, 5., &catalog), 1);
+        assert_eq!(storage.quantity(INGOT), 1);
+        // Only one ore is left, so the next run waits for inputs
+        assert!(!facility.is_running());
+        assert!(facility.blocked().is_some());
+        assert_eq!(storage.quantity(ORE), 1);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{universe::StationService, Dockable, Name, Ship};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+/// Who owns a station and what it offers to ships docked with it
+#[derive(Component, Debug, Default, Clone)]
+pub struct StationInfo {
+    pub faction: Option<String>,
+    pub services: Vec<StationService>,
+}
+
+/// The primary ship and the station it is docked with
+#[derive(Debug, Clone, Copy)]
+pub struct Docked {
+    pub ship: Entity,
+    pub station: Entity,
+}
+
+/// A tab of the station screen providing one service
+///
+/// Tabs get the whole world so they can read and change whatever their
+/// service needs, and are shown for every station offering their service.
+pub trait ServiceTab: Send + Sync + 'static {
+    fn service(&self) -> StationService;
+
+    fn ui(&mut self, ui: &mut egui::Ui, world: &mut World, docked: Docked);
+}
+
+/// Every service tab the station screen can show
+#[derive(Default)]
+pub struct ServiceTabs {
+    tabs: Vec<Box<dyn ServiceTab>>,
+}
+
+impl ServiceTabs {
+    /// Add a tab, replacing any other tab for the same service
+    pub fn register(&mut self, tab: impl ServiceTab) {
+        self.tabs.retain(|t| t.service() != tab.service());
+        self.tabs.push(Box::new(tab));
+    }
+
+    fn get_mut(&mut self, service: StationService) -> Option<&mut dyn ServiceTab> {
+        self.tabs
+            .iter_mut()
+            .find(|tab| tab.service() == service)
+            .map(|tab| tab.as_mut())
+    }
+
+    fn contains(&self, service: StationService) -> bool {
+        self.tabs.iter().any(|tab| tab.service() == service)
+    }
+}
+
+/// State of the station screen, which opens whenever the primary ship docks
+#[derive(Debug, Default)]
+pub struct StationScreen {
+    pub open: bool,
+    pub tab: Option<StationService>,
+    docked: Option<Docked>,
+}
+
+fn primary_docked(world: &mut World) -> Option<Docked> {
+    world
+        .query::<(Entity, &Ship, &Dockable, Option<&Parent>)>()
+        .iter(world)
+        .find(|(_, ship, dockable, _)| ship.primary && dockable.is_docked)
+        .and_then(|(ship, _, _, parent)| {
+            parent.map(|parent| Docked {
+                ship,
+                station: parent.get(),
+            })
+        })
+}
+
+pub fn station_screen_ui(world: &mut World) {
+    let docked = primary_docked(world);
+    let mut screen = world.resource_mut::<StationScreen>();
+    let changed_station = screen.docked.map(|d| d.station) != docked.map(|d| d.station);
+    screen.docked = docked;
+    if changed_station {
+        screen.open = docked.is_some();
+        screen.tab = None;
+    }
+    let docked = match docked {
+        Some(docked) if screen.open => docked,
+        _ => return,
+    };
+    let (mut open, mut tab) = (screen.open, screen.tab);
+
+    let name = world
+        .get::<Name>(docked.station)
+        .map_or("Station".to_string(), |name| name.to_string());
+    let info = world
+        .get::<StationInfo>(docked.station)
+        .cloned()
+        .unwrap_or_default();
+
+    let ctx = world.resource_mut::<EguiContext>().ctx_mut().clone();
+    world.resource_scope(|world, mut tabs: Mut<ServiceTabs>| {
+        if tab.is_none_or(|service| !info.services.contains(&service)) {
+            tab = info
+                .services
+                .iter()
+                .copied()
+                .find(|&service| tabs.contains(service));
+        }
+
+        egui::Window::new(&name)
+            .id(egui::Id::new("station_screen"))
+            .open(&mut open)
+            .default_size([500., 400.])
+            .resizable(true)
+            .show(&ctx, |ui| {
+                if let Some(faction) = &info.faction {
+                    ui.label(format!("Operated by {}", faction));
+                }
+
+                ui.horizontal(|ui| {
+                    for &service in &info.services {
+                        if tabs.contains(service) {
+                            ui.selectable_value(&mut tab, Some(service), service.to_string());
+                        } else {
+                            ui.add_enabled(
+                                false,
+                                egui::SelectableLabel::new(false, service.to_string()),
+                            )
+                            .on_disabled_hover_text("Not available yet");
+                        }
+                    }
+                });
+                ui.separator();
+
+                match tab.and_then(|service| tabs.get_mut(service)) {
+                    Some(service_tab) => service_tab.ui(ui, world, docked),
+                    None if info.services.is_empty() => {
+                        ui.label("This station offers no services");
+                    }
+                    None => {
+                        ui.label("None of this station's services are available yet");
+                    }
+                }
+            });
+    });
+
+    let mut screen = world.resource_mut::<StationScreen>();
+    screen.open = open;
+    screen.tab = tab;
+}
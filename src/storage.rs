@@ -0,0 +1,118 @@
+use crate::{
+    cargo::{capacity_ui, transfer_cargo, CargoHold},
+    item::{ItemCatalog, ItemId},
+    station::{Docked, ServiceTab},
+    universe::StationService,
+};
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+/// The station screen tab for moving cargo between the docked ship and the
+/// station's storage
+#[derive(Debug)]
+pub struct StorageTab {
+    quantity: u64,
+    message: Option<String>,
+}
+
+impl Default for StorageTab {
+    fn default() -> Self {
+        StorageTab {
+            quantity: 1,
+            message: None,
+        }
+    }
+}
+
+/// List the contents of `hold` with a button per item, returning the item
+/// whose button was clicked
+fn hold_ui(
+    ui: &mut egui::Ui,
+    heading: &str,
+    hold: &CargoHold,
+    catalog: &ItemCatalog,
+    button: &str,
+) -> Option<ItemId> {
+    let mut clicked = None;
+    ui.vertical(|ui| {
+        ui.heading(heading);
+        capacity_ui(ui, hold, catalog);
+        ui.separator();
+
+        let mut items: Vec<_> = hold.iter().collect();
+        items.sort_by_key(|(item, _)| *item);
+        if items.is_empty() {
+            ui.label("Empty");
+        }
+        for (item, quantity) in items {
+            ui.horizontal(|ui| {
+                if ui.button(button).clicked() {
+                    clicked = Some(item);
+                }
+                ui.label(format!("{} × {}", quantity, catalog.name(item)));
+            });
+        }
+    });
+    clicked
+}
+
+impl ServiceTab for StorageTab {
+    fn service(&self) -> StationService {
+        StationService::Storage
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, world: &mut World, docked: Docked) {
+        let (ship_hold, station_hold) = match (
+            world.get::<CargoHold>(docked.ship),
+            world.get::<CargoHold>(docked.station),
+        ) {
+            (Some(ship), Some(station)) => (ship.clone(), station.clone()),
+            _ => {
+                ui.label("Storage is unavailable");
+                return;
+            }
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Quantity");
+            ui.add(egui::DragValue::new(&mut self.quantity).clamp_range(1..=1000));
+        });
+
+        let catalog = world.resource::<ItemCatalog>();
+        let mut transfer = None;
+        ui.columns(2, |columns| {
+            if let Some(item) = hold_ui(&mut columns[0], "Ship", &ship_hold, catalog, "Store") {
+                transfer = Some((item, docked.ship, docked.station));
+            }
+            if let Some(item) = hold_ui(
+                &mut columns[1],
+                "Station",
+                &station_hold,
+                catalog,
+                "Retrieve",
+            ) {
+                transfer = Some((item, docked.station, docked.ship));
+            }
+        });
+
+        if let Some((item, from, to)) = transfer {
+            let name = catalog.name(item);
+            // Move whatever is held if fewer than the picked quantity are
+            let quantity = match from == docked.ship {
+                true => ship_hold.quantity(item),
+                false => station_hold.quantity(item),
+            }
+            .min(self.quantity);
+
+            self.message = Some(match transfer_cargo(world, from, to, item, quantity) {
+                Ok(()) => format!("Moved {} {}", quantity, name),
+                Err(e) => format!("Cannot move {}: {}", name, e),
+            });
+        }
+
+        if let Some(message) = &self.message {
+            ui.separator();
+            ui.label(message);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{
+    clock::SimulationClock,
+    gravity::{GravitySettings, Mass},
+    orbit::{OrbitChain, Orbiting},
+    Dockable, Ship, Station,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use heron::prelude::*;
+
+/// Simulated seconds between predicted points
+const PREDICTION_STEP: f32 = 0.05;
+const PREDICTION_STEPS: usize = 600;
+/// Passing a celestial body closer than this is flagged as a close approach
+const CLOSE_APPROACH_DISTANCE: f32 = 40.;
+/// Passing a station closer than this is flagged as an intersection
+const STATION_PASS_DISTANCE: f32 = 15.;
+const MARKER_RADIUS: f32 = 4.;
+
+/// The predicted path of the primary ship
+#[derive(Component)]
+pub struct TrajectoryLine;
+
+/// A highlighted point of interest along the predicted path
+#[derive(Component)]
+pub struct TrajectoryMarker;
+
+pub fn spawn_trajectory_line(mut commands: Commands) {
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Polygon {
+                points: vec![Vec2::ZERO; 2],
+                closed: false,
+            },
+            DrawMode::Stroke(StrokeMode::new(Color::CYAN, 1.)),
+            Transform::from_translation(Vec3::new(0., 0., 1.)),
+        ))
+        .insert(TrajectoryLine);
+}
+
+/// Project the primary ship's path forward under gravity and redraw it,
+/// marking close approaches to celestial bodies and stations along the way
+#[allow(clippy::too_many_arguments)]
+pub fn predict_trajectory(
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    gravity: Res<GravitySettings>,
+    ship_query: Query<(&GlobalTransform, &Velocity, &Dockable, &Ship)>,
+    bodies: Query<(Option<&Orbiting>, Option<&Parent>, &Transform)>,
+    wells: Query<(Entity, &Mass)>,
+    stations: Query<Entity, With<Station>>,
+    mut line_query: Query<(&mut Path, &mut Visibility), With<TrajectoryLine>>,
+    markers: Query<Entity, With<TrajectoryMarker>>,
+) {
+    for marker in markers.iter() {
+        commands.entity(marker).despawn();
+    }
+
+    let (mut path, mut visibility) = match line_query.get_single_mut() {
+        Ok(line) => line,
+        _ => return,
+    };
+    let (transform, velocity, dockable, _) = match ship_query.iter().find(|(_, _, _, s)| s.primary)
+    {
+        Some(ship) => ship,
+        None => return,
+    };
+
+    visibility.is_visible = !dockable.is_docked;
+    if dockable.is_docked {
+        return;
+    }
+
+    let wells: Vec<_> = wells
+        .iter()
+        .map(|(entity, mass)| (OrbitChain::of(entity, &bodies), mass.0))
+        .collect();
+    let stations: Vec<_> = stations
+        .iter()
+        .map(|entity| OrbitChain::of(entity, &bodies))
+        .collect();
+
+    let mut position = transform.translation().truncate();
+    let mut velocity = velocity.linear.truncate();
+    let mut points = vec![position];
+
+    // Last distance to each well and whether the ship was closing in on it,
+    // so the nearest point of each pass can be found
+    let mut approaches: Vec<_> = wells
+        .iter()
+        .map(|(chain, _)| (chain.position_at(clock.elapsed()).distance(position), false))
+        .collect();
+    let mut passed = vec![false; stations.len()];
+
+    for step in 1..=PREDICTION_STEPS {
+        let time = clock.elapsed() + (step as f32 * PREDICTION_STEP) as f64;
+        let well_positions: Vec<_> = wells
+            .iter()
+            .map(|(chain, mass)| (chain.position_at(time), *mass))
+            .collect();
+
+        velocity +=
+            gravity.acceleration_at(position, well_positions.iter().copied()) * PREDICTION_STEP;
+        position += velocity * PREDICTION_STEP;
+        points.push(position);
+
+        for ((well, _), (last, closing)) in well_positions.iter().zip(&mut approaches) {
+            let distance = well.distance(position);
+            if *closing && distance > *last && *last < CLOSE_APPROACH_DISTANCE {
+                spawn_marker(&mut commands, points[points.len() - 2], Color::ORANGE);
+            }
+            *closing = distance < *last;
+            *last = distance;
+        }
+
+        for (station, passed) in stations.iter().zip(&mut passed) {
+            if !*passed && station.position_at(time).distance(position) < STATION_PASS_DISTANCE {
+                spawn_marker(&mut commands, position, Color::GREEN);
+                *passed = true;
+            }
+        }
+    }
+
+    *path = ShapePath::build_as(&shapes::Polygon {
+        points,
+        closed: false,
+    });
+}
+
+fn spawn_marker(commands: &mut Commands, position: Vec2, color: Color) {
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Circle {
+                radius: MARKER_RADIUS,
+                center: Vec2::ZERO,
+            },
+            DrawMode::Stroke(StrokeMode::new(color, 1.)),
+            Transform::from_translation(position.extend(1.)),
+        ))
+        .insert(TrajectoryMarker);
+}
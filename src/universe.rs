@@ -1,62 +1,975 @@
-use bevy::prelude::{debug, Res};
+use crate::{
+    item::{ItemCatalog, ItemId},
+    production::{RecipeBook, RecipeId},
+};
+use bevy::prelude::{debug, Color, Res, Vec2};
 use petgraph::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::HashMap, fmt, fs, io};
 
+mod generator;
+mod route;
+pub use generator::GalaxyParams;
+pub use route::{Route, RouteCost};
+
+/// Index of a [`SolarSystem`] within its [`Galaxy`]
+pub type SystemId = NodeIndex;
+
+/// A graph of solar systems joined by hyperlanes
 #[derive(Debug, Default)]
-pub struct Galaxy(Vec<u32>);
+pub struct Galaxy {
+    graph: UnGraph<SolarSystem, Hyperlane>,
+    names: HashMap<String, SystemId>,
+}
 
 impl Galaxy {
-    pub fn new() -> Self {
-        Galaxy::default()
+    /// Load a galaxy description from a JSON file on disk
+    pub fn from_file(file_path: &str) -> Result<Self, GalaxyError> {
+        let contents = fs::read_to_string(file_path).map_err(|e| GalaxyError::Io {
+            path: file_path.to_string(),
+            source: e,
+        })?;
+        Galaxy::from_json(&contents)
+    }
+
+    /// Parse a galaxy description from a JSON string
+    pub fn from_json(json: &str) -> Result<Self, GalaxyError> {
+        let file: GalaxyFile = serde_json::from_str(json).map_err(GalaxyError::Parse)?;
+        Galaxy::try_from(file)
     }
 
-    pub fn from_file(_file_path: &str) -> Self {
-        println!("TODO: Load Galaxy from file");
-        let graph = random_graph(5, EdgeProbability(0.9));
-        println!("Random graph generated: {:?}", graph);
-        Galaxy::new()
+    /// Save the galaxy as a JSON file which [`Galaxy::from_file`] can read back
+    pub fn to_file(&self, file_path: &str) -> Result<(), GalaxyError> {
+        let mut json = self.to_json()?;
+        json.push('\n');
+        fs::write(file_path, json).map_err(|e| GalaxyError::Io {
+            path: file_path.to_string(),
+            source: e,
+        })
+    }
+
+    /// Write the galaxy description as a pretty-printed JSON string
+    pub fn to_json(&self) -> Result<String, GalaxyError> {
+        serde_json::to_string_pretty(self).map_err(GalaxyError::Serialize)
+    }
+
+    /// Add a solar system, failing if one with the same name already exists
+    pub fn add_system(&mut self, system: SolarSystem) -> Result<SystemId, GalaxyError> {
+        if self.names.contains_key(&system.name) {
+            return Err(GalaxyError::DuplicateSystem(system.name));
+        }
+        let name = system.name.clone();
+        let id = self.graph.add_node(system);
+        self.names.insert(name, id);
+        Ok(id)
+    }
+
+    /// Check every item and recipe the galaxy refers to is defined
+    pub fn check_references(
+        &self,
+        catalog: &ItemCatalog,
+        recipes: &RecipeBook,
+    ) -> Result<(), GalaxyError> {
+        for (_, system) in self.systems() {
+            for belt in &system.belts {
+                if let Some(ore) = belt
+                    .composition
+                    .iter()
+                    .find(|ore| catalog.get(ore.item).is_none())
+                {
+                    return Err(GalaxyError::UnknownItem {
+                        belt: belt.name.clone(),
+                        item: ore.item,
+                    });
+                }
+            }
+
+            for station in system.all_stations() {
+                if let Some(&recipe) = station
+                    .production
+                    .iter()
+                    .find(|&&recipe| recipes.get(recipe).is_none())
+                {
+                    return Err(GalaxyError::UnknownRecipe {
+                        station: station.name.clone(),
+                        recipe,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Join two solar systems with a hyperlane
+    pub fn add_hyperlane(&mut self, a: SystemId, b: SystemId, hyperlane: Hyperlane) -> EdgeIndex {
+        self.graph.update_edge(a, b, hyperlane)
+    }
+
+    pub fn system(&self, id: SystemId) -> Option<&SolarSystem> {
+        self.graph.node_weight(id)
+    }
+
+    pub fn system_mut(&mut self, id: SystemId) -> Option<&mut SolarSystem> {
+        self.graph.node_weight_mut(id)
+    }
+
+    /// Look up the id of a solar system by name
+    pub fn find(&self, name: &str) -> Option<SystemId> {
+        self.names.get(name).copied()
+    }
+
+    /// Iterate over every solar system in the galaxy
+    pub fn systems(&self) -> impl Iterator<Item = (SystemId, &SolarSystem)> {
+        self.graph.node_indices().map(|id| (id, &self.graph[id]))
+    }
+
+    /// Iterate over every hyperlane as `(a, b, hyperlane)`
+    pub fn hyperlanes(&self) -> impl Iterator<Item = (SystemId, SystemId, &Hyperlane)> {
+        self.graph
+            .edge_references()
+            .map(|e| (e.source(), e.target(), e.weight()))
+    }
+
+    /// Iterate over the systems reachable from `id` by a single hyperlane
+    pub fn neighbours(
+        &self,
+        id: SystemId,
+    ) -> impl Iterator<Item = (SystemId, &SolarSystem, &Hyperlane)> {
+        self.graph
+            .edges(id)
+            .map(|e| (e.target(), &self.graph[e.target()], e.weight()))
+    }
+
+    pub fn hyperlane(&self, a: SystemId, b: SystemId) -> Option<&Hyperlane> {
+        self.graph
+            .find_edge(a, b)
+            .and_then(|e| self.graph.edge_weight(e))
+    }
+
+    /// The underlying graph, for use with petgraph's algorithms
+    pub fn graph(&self) -> &UnGraph<SolarSystem, Hyperlane> {
+        &self.graph
+    }
+}
+
+impl PartialEq for Galaxy {
+    fn eq(&self, other: &Self) -> bool {
+        self.systems().eq(other.systems()) && self.hyperlanes().eq(other.hyperlanes())
+    }
+}
+
+impl Serialize for Galaxy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GalaxyFile::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Galaxy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let file = GalaxyFile::deserialize(deserializer)?;
+        Galaxy::try_from(file).map_err(serde::de::Error::custom)
     }
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SystemEntry", into = "SystemEntry")]
 pub struct SolarSystem {
+    name: String,
     star: String,
+    star_class: StarClass,
+    position: Vec2,
     planets: Vec<Planet>,
+    belts: Vec<AsteroidBelt>,
+    stations: Vec<Station>,
 }
 
-#[derive(Debug, Default)]
-struct Planet((String, Option<Vec<Station>>));
+impl SolarSystem {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-#[derive(Debug, Default)]
-struct Station(String);
+    pub fn star(&self) -> &str {
+        &self.star
+    }
 
-#[derive(Debug, Default)]
-struct EdgeProbability(f32);
+    pub fn star_class(&self) -> StarClass {
+        self.star_class
+    }
+
+    /// Position of the system on the galaxy map
+    pub fn position(&self) -> Vec2 {
+        self.position
+    }
+
+    pub fn planets(&self) -> &[Planet] {
+        &self.planets
+    }
+
+    pub fn belts(&self) -> &[AsteroidBelt] {
+        &self.belts
+    }
+
+    /// Stations orbiting the star itself
+    pub fn stations(&self) -> &[Station] {
+        &self.stations
+    }
+
+    /// Orbit radius of the `index`th station orbiting the star, placing
+    /// stations without an explicit radius beyond everything else
+    pub fn station_orbit_radius(&self, index: usize) -> f32 {
+        self.stations[index]
+            .orbit_radius
+            .unwrap_or_else(|| self.outer_radius() + STATION_SPACING * (index + 1) as f32)
+    }
+
+    /// Every station in the system, whether orbiting the star, a planet or
+    /// a moon
+    pub fn all_stations(&self) -> Vec<&Station> {
+        let mut stations: Vec<_> = self.stations.iter().collect();
+        for planet in &self.planets {
+            planet.collect_stations(&mut stations);
+        }
+        stations
+    }
+
+    /// The planet or moon called `name`, including moons of moons
+    pub fn planet_mut(&mut self, name: &str) -> Option<&mut Planet> {
+        self.planets
+            .iter_mut()
+            .find_map(|planet| planet.find_mut(name))
+    }
+
+    /// Add a station orbiting the star, or the planet or moon called
+    /// `planet` if given, failing if there is no such body
+    pub fn add_station(
+        &mut self,
+        planet: Option<&str>,
+        station: Station,
+    ) -> Result<(), GalaxyError> {
+        match planet {
+            Some(name) => self
+                .planet_mut(name)
+                .ok_or_else(|| GalaxyError::UnknownBody(name.to_string()))?
+                .stations
+                .push(station),
+            None => self.stations.push(station),
+        }
+        Ok(())
+    }
+
+    /// Furthest distance from the star reached by a planet's orbit or an
+    /// asteroid belt, or the edge of the star itself if it has neither
+    pub fn outer_radius(&self) -> f32 {
+        let planets = self
+            .planets
+            .iter()
+            .map(|planet| planet.orbit_radius * (1. + planet.eccentricity));
+        let belts = self
+            .belts
+            .iter()
+            .map(|belt| belt.orbit_radius + belt.width / 2.);
+        planets
+            .chain(belts)
+            .fold(self.star_class.size() / 2., f32::max)
+    }
+}
+
+/// A hyperlane joining two solar systems
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hyperlane {
+    pub distance: f32,
+    pub travel_cost: f32,
+}
+
+impl Hyperlane {
+    pub fn new(distance: f32, travel_cost: f32) -> Self {
+        Hyperlane {
+            distance,
+            travel_cost,
+        }
+    }
+}
+
+/// Spectral class of a star, from the hottest and largest to the coolest
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StarClass {
+    O,
+    B,
+    A,
+    F,
+    #[default]
+    G,
+    K,
+    M,
+}
+
+impl StarClass {
+    pub fn color(&self) -> Color {
+        match self {
+            StarClass::O => Color::rgb(0.6, 0.7, 1.0),
+            StarClass::B => Color::rgb(0.75, 0.85, 1.0),
+            StarClass::A => Color::rgb(0.95, 0.95, 1.0),
+            StarClass::F => Color::rgb(1.0, 1.0, 0.85),
+            StarClass::G => Color::rgb(1.0, 0.9, 0.5),
+            StarClass::K => Color::rgb(1.0, 0.7, 0.35),
+            StarClass::M => Color::rgb(1.0, 0.45, 0.3),
+        }
+    }
+
+    /// Width of the star when drawn in its solar system
+    pub fn size(&self) -> f32 {
+        match self {
+            StarClass::O => 90.,
+            StarClass::B => 75.,
+            StarClass::A => 60.,
+            StarClass::F => 55.,
+            StarClass::G => 50.,
+            StarClass::K => 40.,
+            StarClass::M => 30.,
+        }
+    }
+
+    /// Mass of the star for gravity, growing with its area
+    pub fn mass(&self) -> f32 {
+        STAR_DENSITY * self.size().powi(2)
+    }
+}
+
+/// Mass per unit of drawn area for stars and planets
+const STAR_DENSITY: f32 = 2.;
+const ROCKY_DENSITY: f32 = 5.;
+const GAS_GIANT_DENSITY: f32 = 1.5;
+
+/// Seconds per orbit at a radius of 1, scaled by Kepler's third law
+const ORBITAL_PERIOD_FACTOR: f32 = 0.1;
+
+/// Gap between successive stations orbiting a planet
+const STATION_SPACING: f32 = 25.;
+
+/// Period of a circular orbit at `radius`, following Kepler's third law
+fn orbital_period(radius: f32) -> f32 {
+    ORBITAL_PERIOD_FACTOR * radius.powf(1.5)
+}
+
+fn is_zero(value: &f32) -> bool {
+    *value == 0.
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Seed for randomly generating details of the thing called `name`
+///
+/// Uses FNV-1a, which unlike the standard library's hasher gives the same
+/// result on every Rust release.
+pub fn name_seed(name: &str) -> u64 {
+    name.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Broad type of a planet or moon, which decides its default look
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PlanetKind {
+    #[default]
+    Rocky,
+    Desert,
+    Terrestrial,
+    Ocean,
+    Ice,
+    GasGiant,
+}
+
+impl PlanetKind {
+    pub fn color(&self) -> Color {
+        match self {
+            PlanetKind::Rocky => Color::rgb(0.55, 0.5, 0.45),
+            PlanetKind::Desert => Color::rgb(0.85, 0.45, 0.2),
+            PlanetKind::Terrestrial => Color::rgb(0.25, 0.6, 0.3),
+            PlanetKind::Ocean => Color::rgb(0.15, 0.35, 0.85),
+            PlanetKind::Ice => Color::rgb(0.8, 0.9, 0.95),
+            PlanetKind::GasGiant => Color::rgb(0.85, 0.7, 0.5),
+        }
+    }
+
+    /// Default width of a planet of this kind when drawn
+    pub fn size(&self) -> f32 {
+        match self {
+            PlanetKind::Rocky | PlanetKind::Ice => 6.,
+            PlanetKind::Desert => 8.,
+            PlanetKind::Terrestrial | PlanetKind::Ocean => 10.,
+            PlanetKind::GasGiant => 20.,
+        }
+    }
+
+    /// Mass per unit of drawn area
+    pub fn density(&self) -> f32 {
+        match self {
+            PlanetKind::GasGiant => GAS_GIANT_DENSITY,
+            _ => ROCKY_DENSITY,
+        }
+    }
+}
+
+/// A planet or moon, along with everything orbiting it
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Planet {
+    name: String,
+    #[serde(default)]
+    kind: PlanetKind,
+    /// Distance from the body this orbits, or the semi-major axis of an
+    /// elliptical orbit
+    orbit_radius: f32,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    eccentricity: f32,
+    /// Seconds per orbit, derived from the orbit radius if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    orbital_period: Option<f32>,
+    /// Defaults to the size of the planet's kind
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    moons: Vec<Planet>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    stations: Vec<Station>,
+}
+
+impl Planet {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn orbit_radius(&self) -> f32 {
+        self.orbit_radius
+    }
+
+    pub fn eccentricity(&self) -> f32 {
+        self.eccentricity
+    }
+
+    pub fn orbital_period(&self) -> f32 {
+        self.orbital_period
+            .unwrap_or_else(|| orbital_period(self.orbit_radius))
+    }
+
+    pub fn size(&self) -> f32 {
+        self.size.unwrap_or_else(|| self.kind.size())
+    }
+
+    pub fn color(&self) -> Color {
+        self.kind.color()
+    }
+
+    /// Mass of the planet for gravity, growing with its area
+    pub fn mass(&self) -> f32 {
+        self.kind.density() * self.size().powi(2)
+    }
+
+    pub fn moons(&self) -> &[Planet] {
+        &self.moons
+    }
+
+    pub fn stations(&self) -> &[Station] {
+        &self.stations
+    }
+
+    /// Add the stations orbiting this planet and its moons to `stations`
+    fn collect_stations<'a>(&'a self, stations: &mut Vec<&'a Station>) {
+        stations.extend(&self.stations);
+        for moon in &self.moons {
+            moon.collect_stations(stations);
+        }
+    }
+
+    /// This planet if it is called `name`, or else its moon of that name
+    fn find_mut(&mut self, name: &str) -> Option<&mut Planet> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.moons.iter_mut().find_map(|moon| moon.find_mut(name))
+    }
+
+    /// Orbit radius of the `index`th station, placing stations without an
+    /// explicit radius beyond the outermost moon
+    pub fn station_orbit_radius(&self, index: usize) -> f32 {
+        let station = &self.stations[index];
+        station.orbit_radius.unwrap_or_else(|| {
+            let moon_radius = self
+                .moons
+                .iter()
+                .map(|moon| moon.orbit_radius)
+                .fold(0., f32::max);
+            moon_radius + STATION_SPACING * (index + 1) as f32
+        })
+    }
+}
+
+/// Something a station offers to ships docked with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StationService {
+    Market,
+    Refuel,
+    Repair,
+    Shipyard,
+    Missions,
+    Storage,
+    Production,
+}
+
+impl fmt::Display for StationService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A space station orbiting a planet or star
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Station {
+    name: String,
+    /// Distance from the planet, placing the station beyond its moons if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    orbit_radius: Option<f32>,
+    /// Seconds per orbit, derived from the orbit radius if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    orbital_period: Option<f32>,
+    /// Radians around its orbit at the start of the game, spread out from
+    /// neighbouring stations if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    phase: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    faction: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    services: Vec<StationService>,
+    /// Angles in degrees around the hull at which ships must dock, or
+    /// anywhere if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    docking_ports: Vec<f32>,
+    /// Recipe run by each of the station's production facilities
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    production: Vec<RecipeId>,
+}
+
+impl Station {
+    /// A station in a circular orbit of `orbit_radius`
+    pub fn new(
+        name: &str,
+        orbit_radius: f32,
+        faction: Option<&str>,
+        services: Vec<StationService>,
+    ) -> Self {
+        Station {
+            name: name.to_string(),
+            orbit_radius: Some(orbit_radius),
+            faction: faction.map(|faction| faction.to_string()),
+            services,
+            ..Default::default()
+        }
+    }
+
+    /// Start the station `phase` radians around its orbit
+    pub fn with_phase(mut self, phase: f32) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn phase(&self) -> Option<f32> {
+        self.phase
+    }
+
+    /// Seconds per orbit when orbiting at `radius`, see [`Planet::station_orbit_radius`]
+    pub fn orbital_period(&self, radius: f32) -> f32 {
+        self.orbital_period
+            .unwrap_or_else(|| orbital_period(radius))
+    }
+
+    pub fn faction(&self) -> Option<&str> {
+        self.faction.as_deref()
+    }
+
+    pub fn services(&self) -> &[StationService] {
+        &self.services
+    }
+
+    pub fn offers(&self, service: StationService) -> bool {
+        self.services.contains(&service)
+    }
+
+    pub fn production(&self) -> &[RecipeId] {
+        &self.production
+    }
+
+    /// Outward direction of each docking port
+    pub fn docking_ports(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.docking_ports
+            .iter()
+            .map(|angle| Vec2::from_angle(angle.to_radians()))
+    }
+}
+
+/// Relative amount of one ore making up the asteroids of a belt
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OreShare {
+    pub item: ItemId,
+    pub share: f32,
+}
+
+/// A ring of minable asteroids orbiting a star
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AsteroidBelt {
+    name: String,
+    /// Distance of the middle of the belt from the star
+    orbit_radius: f32,
+    /// Distance between the inner and outer edges of the belt
+    #[serde(default = "default_belt_width")]
+    width: f32,
+    #[serde(default = "default_asteroid_count")]
+    asteroids: usize,
+    /// Units of ore in each asteroid when untouched
+    #[serde(default = "default_asteroid_reserve")]
+    reserve: f32,
+    composition: Vec<OreShare>,
+}
+
+fn default_belt_width() -> f32 {
+    20.
+}
+
+fn default_asteroid_count() -> usize {
+    24
+}
+
+fn default_asteroid_reserve() -> f32 {
+    50.
+}
+
+impl AsteroidBelt {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn orbit_radius(&self) -> f32 {
+        self.orbit_radius
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Number of asteroids in the belt
+    pub fn asteroids(&self) -> usize {
+        self.asteroids
+    }
+
+    pub fn reserve(&self) -> f32 {
+        self.reserve
+    }
+
+    pub fn composition(&self) -> &[OreShare] {
+        &self.composition
+    }
+
+    /// Seconds per orbit for an asteroid at `radius`
+    pub fn orbital_period(&self, radius: f32) -> f32 {
+        orbital_period(radius)
+    }
+}
+
+/// Errors which can occur while loading or saving a [`Galaxy`]
+#[derive(Debug)]
+pub enum GalaxyError {
+    Io { path: String, source: io::Error },
+    Parse(serde_json::Error),
+    Serialize(serde_json::Error),
+    DuplicateSystem(String),
+    UnknownSystem(String),
+    UnknownBody(String),
+    SelfHyperlane(String),
+    DuplicateHyperlane { from: String, to: String },
+    UnknownItem { belt: String, item: ItemId },
+    UnknownRecipe { station: String, recipe: RecipeId },
+}
+
+impl fmt::Display for GalaxyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GalaxyError::Io { path, source } => write!(f, "could not access {}: {}", path, source),
+            GalaxyError::Parse(e) => write!(f, "malformed galaxy description: {}", e),
+            GalaxyError::Serialize(e) => write!(f, "could not serialize galaxy: {}", e),
+            GalaxyError::DuplicateSystem(name) => {
+                write!(f, "solar system \"{}\" is defined more than once", name)
+            }
+            GalaxyError::UnknownSystem(name) => {
+                write!(f, "hyperlane references unknown solar system \"{}\"", name)
+            }
+            GalaxyError::UnknownBody(name) => {
+                write!(f, "no planet or moon called \"{}\"", name)
+            }
+            GalaxyError::SelfHyperlane(name) => {
+                write!(f, "hyperlane leads from \"{}\" back to itself", name)
+            }
+            GalaxyError::DuplicateHyperlane { from, to } => write!(
+                f,
+                "hyperlane between \"{}\" and \"{}\" is defined more than once",
+                from, to
+            ),
+            GalaxyError::UnknownItem { belt, item } => {
+                write!(f, "belt \"{}\" contains unknown item {}", belt, item)
+            }
+            GalaxyError::UnknownRecipe { station, recipe } => {
+                write!(
+                    f,
+                    "station \"{}\" produces with unknown recipe {}",
+                    station, recipe
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for GalaxyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GalaxyError::Io { source, .. } => Some(source),
+            GalaxyError::Parse(e) | GalaxyError::Serialize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// On-disk representation of a galaxy, as found in world/galaxy.json
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GalaxyFile {
+    systems: Vec<SolarSystem>,
+    #[serde(default)]
+    hyperlanes: Vec<HyperlaneEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SystemEntry {
+    name: String,
+    star: String,
+    #[serde(default)]
+    star_class: StarClass,
+    #[serde(default)]
+    position: [f32; 2],
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    planets: Vec<Planet>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    belts: Vec<AsteroidBelt>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    stations: Vec<Station>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HyperlaneEntry {
+    from: String,
+    to: String,
+    /// Defaults to the distance between the two systems on the map
+    distance: Option<f32>,
+    /// Defaults to the distance of the hyperlane
+    travel_cost: Option<f32>,
+}
+
+impl TryFrom<GalaxyFile> for Galaxy {
+    type Error = GalaxyError;
+
+    fn try_from(file: GalaxyFile) -> Result<Self, Self::Error> {
+        let mut galaxy = Galaxy::default();
+
+        for system in file.systems {
+            galaxy.add_system(system)?;
+        }
+
+        let lookup = |name: &String| {
+            galaxy
+                .find(name)
+                .ok_or_else(|| GalaxyError::UnknownSystem(name.clone()))
+        };
+
+        let hyperlanes = file
+            .hyperlanes
+            .iter()
+            .map(|lane| {
+                let (a, b) = (lookup(&lane.from)?, lookup(&lane.to)?);
+                let distance = lane
+                    .distance
+                    .unwrap_or_else(|| galaxy.graph[a].position.distance(galaxy.graph[b].position));
+                let travel_cost = lane.travel_cost.unwrap_or(distance);
+                Ok((a, b, Hyperlane::new(distance, travel_cost)))
+            })
+            .collect::<Result<Vec<_>, GalaxyError>>()?;
+
+        for (a, b, hyperlane) in hyperlanes {
+            if a == b {
+                return Err(GalaxyError::SelfHyperlane(galaxy.graph[a].name.clone()));
+            }
+            if galaxy.hyperlane(a, b).is_some() {
+                return Err(GalaxyError::DuplicateHyperlane {
+                    from: galaxy.graph[a].name.clone(),
+                    to: galaxy.graph[b].name.clone(),
+                });
+            }
+            galaxy.add_hyperlane(a, b, hyperlane);
+        }
+
+        Ok(galaxy)
+    }
+}
+
+impl From<&Galaxy> for GalaxyFile {
+    fn from(galaxy: &Galaxy) -> Self {
+        GalaxyFile {
+            systems: galaxy.systems().map(|(_, system)| system.clone()).collect(),
+            hyperlanes: galaxy
+                .hyperlanes()
+                .map(|(a, b, hyperlane)| HyperlaneEntry {
+                    from: galaxy.graph[a].name.clone(),
+                    to: galaxy.graph[b].name.clone(),
+                    distance: Some(hyperlane.distance),
+                    travel_cost: Some(hyperlane.travel_cost),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<SystemEntry> for SolarSystem {
+    fn from(entry: SystemEntry) -> Self {
+        SolarSystem {
+            name: entry.name,
+            star: entry.star,
+            star_class: entry.star_class,
+            position: Vec2::from(entry.position),
+            planets: entry.planets,
+            belts: entry.belts,
+            stations: entry.stations,
+        }
+    }
+}
+
+impl From<SolarSystem> for SystemEntry {
+    fn from(system: SolarSystem) -> Self {
+        SystemEntry {
+            name: system.name,
+            star: system.star,
+            star_class: system.star_class,
+            position: system.position.to_array(),
+            planets: system.planets,
+            belts: system.belts,
+            stations: system.stations,
+        }
+    }
+}
+
+/// Probability of a hyperlane forming between two systems, clamped to `[0, 1]`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EdgeProbability(f32);
 
-#[allow(dead_code)]
 impl EdgeProbability {
-    fn new(p: f32) -> Self {
+    pub fn new(p: f32) -> Self {
         EdgeProbability(p.clamp(0., 1.))
     }
 
-    fn get(&self) -> f32 {
+    pub fn get(&self) -> f32 {
         self.0
     }
 }
 
 pub fn debug_universe(universe: Res<Galaxy>) {
     debug!("Debug Universe {:?}", universe);
+    for (id, system) in universe.systems() {
+        let neighbours = universe
+            .neighbours(id)
+            .map(|(_, neighbour, _)| neighbour.name())
+            .collect::<Vec<_>>();
+        debug!("{} -> {:?}", system.name(), neighbours);
+    }
 }
 
-fn random_graph(order: u32, probability: EdgeProbability) -> UnGraph<u32, ()> {
-    let mut edges = Vec::<(u32, u32)>::new();
-    for i in 0..=order {
-        for j in i..=order {
-            if i != j && rand::random::<f32>() < probability.get() {
-                edges.push((i, j));
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn world_galaxy_round_trips() -> Result<(), GalaxyError> {
+        let galaxy = Galaxy::from_file("world/galaxy.json")?;
+        assert_eq!(Galaxy::from_json(&galaxy.to_json()?)?, galaxy);
+        Ok(())
+    }
+
+    #[test]
+    fn generated_galaxy_round_trips() -> Result<(), GalaxyError> {
+        let galaxy = Galaxy::generate(&GalaxyParams::new(42));
+        assert_eq!(Galaxy::from_json(&galaxy.to_json()?)?, galaxy);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_system_is_rejected() {
+        let json = r#"{
+            "systems": [{"name": "Sol", "star": "Sol"}],
+            "hyperlanes": [{"from": "Sol", "to": "Vega"}]
+        }"#;
+        assert!(matches!(
+            Galaxy::from_json(json),
+            Err(GalaxyError::UnknownSystem(name)) if name == "Vega"
+        ));
+    }
+
+    #[test]
+    fn duplicate_system_is_rejected() {
+        let json = r#"{
+            "systems": [{"name": "Sol", "star": "Sol"}, {"name": "Sol", "star": "Sun"}]
+        }"#;
+        assert!(matches!(
+            Galaxy::from_json(json),
+            Err(GalaxyError::DuplicateSystem(name)) if name == "Sol"
+        ));
+    }
+
+    #[test]
+    fn self_hyperlane_is_rejected() {
+        let json = r#"{
+            "systems": [{"name": "Sol", "star": "Sol"}],
+            "hyperlanes": [{"from": "Sol", "to": "Sol"}]
+        }"#;
+        assert!(matches!(
+            Galaxy::from_json(json),
+            Err(GalaxyError::SelfHyperlane(name)) if name == "Sol"
+        ));
+    }
+
+    #[test]
+    fn duplicate_hyperlane_is_rejected() {
+        let json = r#"{
+            "systems": [{"name": "Sol", "star": "Sol"}, {"name": "Vega", "star": "Vega"}],
+            "hyperlanes": [{"from": "Sol", "to": "Vega"}, {"from": "Vega", "to": "Sol"}]
+        }"#;
+        assert!(matches!(
+            Galaxy::from_json(json),
+            Err(GalaxyError::DuplicateHyperlane { from, to }) if from == "Vega" && to == "Sol"
+        ));
     }
 
-    UnGraph::from_edges(edges)
+    #[test]
+    fn malformed_json_is_rejected() {
+        let json = r#"{"systems": [{"name": "Sol"}]}"#;
+        assert!(matches!(
+            Galaxy::from_json(json),
+            Err(GalaxyError::Parse(_))
+        ));
+    }
 }
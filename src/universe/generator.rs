@@ -0,0 +1,395 @@
+use super::{
+    AsteroidBelt, EdgeProbability, Galaxy, Hyperlane, OreShare, Planet, PlanetKind, SolarSystem,
+    StarClass, Station, StationService, SystemId,
+};
+use crate::{item::ItemId, production::RecipeId};
+use bevy::prelude::Vec2;
+use petgraph::unionfind::UnionFind;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashSet;
+
+/// Relative frequency of each star class, most stars being small and cool
+const STAR_CLASS_WEIGHTS: [(StarClass, u32); 7] = [
+    (StarClass::O, 2),
+    (StarClass::B, 3),
+    (StarClass::A, 5),
+    (StarClass::F, 8),
+    (StarClass::G, 12),
+    (StarClass::K, 20),
+    (StarClass::M, 50),
+];
+
+const PLANET_LETTERS: &str = "bcdefghijk";
+const MOON_NUMERALS: [&str; 4] = ["I", "II", "III", "IV"];
+const STATION_PROBABILITY: f64 = 0.3;
+const FACTIONS: [&str; 4] = [
+    "Terran Union",
+    "Free Traders",
+    "Mining Guild",
+    "Independent",
+];
+const OPTIONAL_SERVICES: [StationService; 6] = [
+    StationService::Market,
+    StationService::Repair,
+    StationService::Shipyard,
+    StationService::Missions,
+    StationService::Storage,
+    StationService::Production,
+];
+/// Recipes generated stations may produce with, by their IDs in
+/// world/recipes.json
+const STATION_RECIPES: [RecipeId; 7] = [
+    RecipeId(1),
+    RecipeId(2),
+    RecipeId(3),
+    RecipeId(4),
+    RecipeId(5),
+    RecipeId(6),
+    RecipeId(7),
+];
+const MAX_FACILITIES: usize = 3;
+
+const BELT_PROBABILITY: f64 = 0.5;
+/// Ores making up generated asteroid belts, by their IDs in world/items.json
+const BELT_ORES: [ItemId; 4] = [ItemId(1), ItemId(2), ItemId(3), ItemId(4)];
+
+const SYLLABLES: [&str; 24] = [
+    "al", "be", "ce", "dra", "e", "fo", "ga", "hy", "io", "ka", "lo", "ma", "ne", "or", "pha",
+    "qu", "ri", "sa", "tau", "u", "ve", "xi", "ya", "zor",
+];
+
+/// Parameters for procedurally generating a [`Galaxy`]
+///
+/// The same parameters always produce the same galaxy.
+#[derive(Debug, Clone)]
+pub struct GalaxyParams {
+    pub seed: u64,
+    pub systems: usize,
+    pub edge_probability: EdgeProbability,
+    pub min_degree: usize,
+    pub max_degree: usize,
+    /// Radius of the disc the systems are scattered across on the galaxy map
+    pub radius: f32,
+}
+
+impl Default for GalaxyParams {
+    fn default() -> Self {
+        GalaxyParams {
+            seed: 0,
+            systems: 20,
+            edge_probability: EdgeProbability::new(0.3),
+            min_degree: 1,
+            max_degree: 4,
+            radius: 500.,
+        }
+    }
+}
+
+impl GalaxyParams {
+    pub fn new(seed: u64) -> Self {
+        GalaxyParams {
+            seed,
+            ..Default::default()
+        }
+    }
+}
+
+impl Galaxy {
+    /// Generate a connected galaxy from a seed and parameters
+    pub fn generate(params: &GalaxyParams) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(params.seed);
+        let mut galaxy = Galaxy::default();
+        let max_degree = params.max_degree.max(params.min_degree).max(1);
+
+        let mut names = HashSet::new();
+        let ids = (0..params.systems)
+            .map(|_| {
+                let name = unique_name(&mut rng, &mut names);
+                let position = random_position(&mut rng, params.radius);
+                let star_class = StarClass::random(&mut rng);
+                let system = SolarSystem::generate(&name, position, star_class, rng.gen());
+                galaxy
+                    .add_system(system)
+                    .expect("Expected generated system names to be unique")
+            })
+            .collect::<Vec<_>>();
+
+        // Consider every pair of systems, nearest first, so lanes favour short hops
+        let mut pairs = Vec::new();
+        for i in 0..ids.len() {
+            for j in (i + 1)..ids.len() {
+                pairs.push((galaxy.distance(ids[i], ids[j]), i, j));
+            }
+        }
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut degree = vec![0; ids.len()];
+        let mut components = UnionFind::<usize>::new(ids.len());
+        let connect = |galaxy: &mut Galaxy, degree: &mut [usize], i: usize, j: usize| {
+            let distance = galaxy.distance(ids[i], ids[j]);
+            galaxy.add_hyperlane(ids[i], ids[j], Hyperlane::new(distance, distance));
+            degree[i] += 1;
+            degree[j] += 1;
+        };
+
+        for &(_, i, j) in pairs.iter() {
+            let roll = rng.gen::<f32>();
+            if degree[i] < max_degree
+                && degree[j] < max_degree
+                && roll < params.edge_probability.get()
+            {
+                connect(&mut galaxy, &mut degree, i, j);
+                components.union(i, j);
+            }
+        }
+
+        // Join disconnected clusters by their shortest bridge, relaxing the
+        // degree limit only when there is no other way to connect them
+        for relaxed in [false, true] {
+            for &(_, i, j) in pairs.iter() {
+                if !components.equiv(i, j)
+                    && (relaxed || (degree[i] < max_degree && degree[j] < max_degree))
+                {
+                    connect(&mut galaxy, &mut degree, i, j);
+                    components.union(i, j);
+                }
+            }
+        }
+
+        // Top up systems with too few lanes with their nearest free neighbours
+        for &(_, i, j) in pairs.iter() {
+            if (degree[i] < params.min_degree || degree[j] < params.min_degree)
+                && degree[i] < max_degree
+                && degree[j] < max_degree
+                && galaxy.hyperlane(ids[i], ids[j]).is_none()
+            {
+                connect(&mut galaxy, &mut degree, i, j);
+            }
+        }
+
+        galaxy
+    }
+
+    fn distance(&self, a: SystemId, b: SystemId) -> f32 {
+        self.graph[a].position.distance(self.graph[b].position)
+    }
+}
+
+impl SolarSystem {
+    /// Generate the planets, moons and stations of a system around a star
+    ///
+    /// The same arguments always produce the same system.
+    pub fn generate(name: &str, position: Vec2, star_class: StarClass, seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        let planet_count = match star_class {
+            StarClass::O | StarClass::B => rng.gen_range(0..=3),
+            StarClass::A | StarClass::F => rng.gen_range(1..=6),
+            StarClass::G => rng.gen_range(2..=8),
+            StarClass::K => rng.gen_range(1..=6),
+            StarClass::M => rng.gen_range(0..=4),
+        };
+
+        // Space orbits out geometrically from just beyond the star, roughly
+        // following the Titius-Bode pattern of real systems
+        let mut orbit_radius = star_class.size() + rng.gen_range(30.0..70.0);
+        let planets = PLANET_LETTERS
+            .chars()
+            .take(planet_count)
+            .enumerate()
+            .map(|(i, letter)| {
+                let planet_name = format!("{} {}", name, letter);
+                let kind = planet_kind(&mut rng, i as f32 / planet_count as f32);
+                // Outer planets are more often gas giants with many moons
+                let max_moons = (i * MOON_NUMERALS.len() / planet_count.max(1)).max(1);
+                let moon_spacing = kind.size() / 2. + 10.;
+                let moons = (0..rng.gen_range(0..=max_moons))
+                    .map(|m| Planet {
+                        name: format!("{} {}", planet_name, MOON_NUMERALS[m]),
+                        kind: *[PlanetKind::Rocky, PlanetKind::Ice]
+                            .choose(&mut rng)
+                            .expect("Expected moon kinds"),
+                        orbit_radius: moon_spacing + 12. * m as f32,
+                        size: Some(rng.gen_range(2.0..4.0)),
+                        ..Default::default()
+                    })
+                    .collect::<Vec<_>>();
+                let stations = match rng.gen_bool(STATION_PROBABILITY) {
+                    true => vec![random_station(&mut rng, &planet_name)],
+                    false => vec![],
+                };
+
+                let planet = Planet {
+                    name: planet_name,
+                    kind,
+                    orbit_radius,
+                    eccentricity: rng.gen_range(0.0..0.1),
+                    size: Some(kind.size() * rng.gen_range(0.8..1.2)),
+                    moons,
+                    stations,
+                    ..Default::default()
+                };
+                orbit_radius *= rng.gen_range(1.4..1.9);
+                planet
+            })
+            .collect();
+
+        // A belt takes the next free orbit beyond the planets
+        let belts = match rng.gen_bool(BELT_PROBABILITY) {
+            true => vec![random_belt(&mut rng, name, orbit_radius)],
+            false => vec![],
+        };
+
+        SolarSystem {
+            name: name.to_string(),
+            star: name.to_string(),
+            star_class,
+            position,
+            planets,
+            belts,
+            stations: vec![],
+        }
+    }
+}
+
+impl StarClass {
+    /// Pick a star class, weighted by how common each class is
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        STAR_CLASS_WEIGHTS
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .map(|(class, _)| *class)
+            .expect("Expected star class weights to be valid")
+    }
+}
+
+/// Pick a planet kind for an orbit `depth` of the way out from the star,
+/// inner planets being hot and rocky and outer ones cold or gaseous
+fn planet_kind(rng: &mut ChaCha8Rng, depth: f32) -> PlanetKind {
+    let kinds: &[PlanetKind] = match depth {
+        d if d < 0.25 => &[PlanetKind::Rocky, PlanetKind::Desert],
+        d if d < 0.5 => &[
+            PlanetKind::Desert,
+            PlanetKind::Terrestrial,
+            PlanetKind::Ocean,
+        ],
+        d if d < 0.75 => &[PlanetKind::GasGiant, PlanetKind::Rocky],
+        _ => &[PlanetKind::GasGiant, PlanetKind::Ice],
+    };
+    *kinds.choose(rng).expect("Expected planet kinds")
+}
+
+fn random_station(rng: &mut ChaCha8Rng, planet_name: &str) -> Station {
+    let mut services = vec![StationService::Refuel];
+    services.extend(
+        OPTIONAL_SERVICES
+            .iter()
+            .filter(|_| rng.gen_bool(0.5))
+            .copied(),
+    );
+    let production = match services.contains(&StationService::Production) {
+        true => {
+            let count = rng.gen_range(1..=MAX_FACILITIES);
+            STATION_RECIPES
+                .choose_multiple(rng, count)
+                .copied()
+                .collect()
+        }
+        false => vec![],
+    };
+
+    Station {
+        name: format!("{} Station", planet_name),
+        faction: FACTIONS.choose(rng).map(|faction| faction.to_string()),
+        services,
+        production,
+        ..Default::default()
+    }
+}
+
+fn random_belt(rng: &mut ChaCha8Rng, system_name: &str, orbit_radius: f32) -> AsteroidBelt {
+    let mut composition: Vec<_> = BELT_ORES
+        .iter()
+        .filter_map(|&item| {
+            if !rng.gen_bool(0.6) {
+                return None;
+            }
+            Some(OreShare {
+                item,
+                share: rng.gen_range(0.1..1.0),
+            })
+        })
+        .collect();
+    if composition.is_empty() {
+        composition.push(OreShare {
+            item: *BELT_ORES.choose(rng).expect("Expected belt ores"),
+            share: 1.,
+        });
+    }
+
+    AsteroidBelt {
+        name: format!("{} Belt", system_name),
+        orbit_radius,
+        width: rng.gen_range(15.0..40.0),
+        asteroids: rng.gen_range(12..=32),
+        reserve: rng.gen_range(30.0..80.0),
+        composition,
+    }
+}
+
+fn unique_name(rng: &mut ChaCha8Rng, taken: &mut HashSet<String>) -> String {
+    let syllables = rng.gen_range(2..=3);
+    let mut name = (0..syllables)
+        .map(|_| *SYLLABLES.choose(rng).expect("Expected syllables"))
+        .collect::<String>();
+    name[..1].make_ascii_uppercase();
+
+    let mut candidate = name.clone();
+    let mut suffix = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{} {}", name, suffix);
+        suffix += 1;
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+fn random_position(rng: &mut ChaCha8Rng, radius: f32) -> Vec2 {
+    // Square root keeps the systems evenly spread across the disc
+    let r = radius * rng.gen::<f32>().sqrt();
+    let theta = rng.gen_range(0.0..std::f32::consts::TAU);
+    Vec2::new(r * theta.cos(), r * theta.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use petgraph::algo::connected_components;
+
+    const SEEDS: [u64; 5] = [0, 1, 7, 42, 0xdead_beef];
+
+    #[test]
+    fn same_params_give_same_galaxy() {
+        for seed in SEEDS {
+            let params = GalaxyParams::new(seed);
+            assert_eq!(Galaxy::generate(&params), Galaxy::generate(&params));
+        }
+    }
+
+    #[test]
+    fn generated_galaxy_is_connected() {
+        for seed in SEEDS {
+            let galaxy = Galaxy::generate(&GalaxyParams::new(seed));
+            assert_eq!(connected_components(galaxy.graph()), 1, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn generated_galaxy_has_requested_systems() {
+        for seed in SEEDS {
+            let params = GalaxyParams::new(seed);
+            let galaxy = Galaxy::generate(&params);
+            assert_eq!(galaxy.graph().node_count(), params.systems);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use super::{Galaxy, Hyperlane, SystemId};
+use petgraph::algo::astar;
+
+/// What a route planner should minimise when crossing hyperlanes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RouteCost {
+    Hops,
+    #[default]
+    Distance,
+    TravelCost,
+}
+
+impl RouteCost {
+    fn of(&self, hyperlane: &Hyperlane) -> f32 {
+        match self {
+            RouteCost::Hops => 1.,
+            RouteCost::Distance => hyperlane.distance,
+            RouteCost::TravelCost => hyperlane.travel_cost,
+        }
+    }
+}
+
+/// An ordered list of systems to jump through, starting at the origin
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub systems: Vec<SystemId>,
+    pub cost: f32,
+}
+
+impl Route {
+    /// Number of hyperlane jumps along the route
+    pub fn hops(&self) -> usize {
+        self.systems.len() - 1
+    }
+
+    /// The system to jump to after `current`, if `current` is on the route
+    pub fn next_after(&self, current: SystemId) -> Option<SystemId> {
+        self.systems
+            .iter()
+            .position(|&id| id == current)
+            .and_then(|i| self.systems.get(i + 1).copied())
+    }
+
+    pub fn contains(&self, id: SystemId) -> bool {
+        self.systems.contains(&id)
+    }
+}
+
+impl Galaxy {
+    /// Find the cheapest route between two systems
+    pub fn route(&self, from: SystemId, to: SystemId, cost: RouteCost) -> Option<Route> {
+        self.route_by(from, to, |hyperlane| cost.of(hyperlane))
+    }
+
+    /// Find the cheapest route between two systems using a custom cost per
+    /// hyperlane, such as fuel, danger or tolls
+    ///
+    /// Costs must not be negative.
+    pub fn route_by<F>(&self, from: SystemId, to: SystemId, mut cost: F) -> Option<Route>
+    where
+        F: FnMut(&Hyperlane) -> f32,
+    {
+        self.system(from)?;
+        self.system(to)?;
+
+        astar(
+            &self.graph,
+            from,
+            |id| id == to,
+            |edge| cost(edge.weight()),
+            |_| 0.,
+        )
+        .map(|(cost, systems)| Route { systems, cost })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::universe::SolarSystem;
+
+    /// A galaxy where Alpha reaches Delta by one long hyperlane or three
+    /// short ones, and Echo is cut off from the rest
+    fn galaxy() -> (Galaxy, [SystemId; 5]) {
+        let mut galaxy = Galaxy::default();
+        let ids = ["Alpha", "Bravo", "Charlie", "Delta", "Echo"].map(|name| {
+            galaxy
+                .add_system(SolarSystem {
+                    name: name.to_string(),
+                    ..Default::default()
+                })
+                .expect("Expected test system names to be unique")
+        });
+        let [alpha, bravo, charlie, delta, _] = ids;
+        galaxy.add_hyperlane(alpha, delta, Hyperlane::new(100., 20.));
+        galaxy.add_hyperlane(alpha, bravo, Hyperlane::new(10., 50.));
+        galaxy.add_hyperlane(bravo, charlie, Hyperlane::new(10., 50.));
+        galaxy.add_hyperlane(charlie, delta, Hyperlane::new(10., 50.));
+        (galaxy, ids)
+    }
+
+    #[test]
+    fn fewest_hops_and_shortest_distance_diverge() {
+        let (galaxy, [alpha, bravo, charlie, delta, _]) = galaxy();
+
+        let hops = galaxy.route(alpha, delta, RouteCost::Hops).unwrap();
+        assert_eq!(hops.systems, vec![alpha, delta]);
+        assert_eq!(hops.cost, 1.);
+
+        let distance = galaxy.route(alpha, delta, RouteCost::Distance).unwrap();
+        assert_eq!(distance.systems, vec![alpha, bravo, charlie, delta]);
+        assert_eq!(distance.cost, 30.);
+        assert_eq!(distance.hops(), 3);
+    }
+
+    #[test]
+    fn custom_cost_decides_route() {
+        let (galaxy, [alpha, bravo, charlie, delta, _]) = galaxy();
+
+        // Distance plus a toll charged on every jump
+        let toll = |toll: f32| move |hyperlane: &Hyperlane| hyperlane.distance + toll;
+
+        let route = galaxy.route_by(alpha, delta, toll(25.)).unwrap();
+        assert_eq!(route.systems, vec![alpha, bravo, charlie, delta]);
+        assert_eq!(route.cost, 105.);
+
+        let route = galaxy.route_by(alpha, delta, toll(50.)).unwrap();
+        assert_eq!(route.systems, vec![alpha, delta]);
+        assert_eq!(route.cost, 150.);
+    }
+
+    #[test]
+    fn unreachable_destination_has_no_route() {
+        let (galaxy, [alpha, _, _, _, echo]) = galaxy();
+        assert_eq!(galaxy.route(alpha, echo, RouteCost::Distance), None);
+    }
+
+    #[test]
+    fn route_to_origin_stays_put() {
+        let (galaxy, [alpha, ..]) = galaxy();
+        let route = galaxy.route(alpha, alpha, RouteCost::Hops).unwrap();
+        assert_eq!(route.systems, vec![alpha]);
+        assert_eq!(route.cost, 0.);
+        assert_eq!(route.hops(), 0);
+    }
+}